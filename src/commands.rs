@@ -1,8 +1,14 @@
+use crate::exif;
+use crate::probe::{self, MediaInfo};
+use crate::subtitle::{self, Retime, SubtitleFormat};
 use anyhow::{anyhow, Context, Result};
 use regex::Regex;
+use std::fmt::{self, Write};
 use std::io::{BufReader, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 pub fn check_ffmpeg_installed() -> Result<()> {
     match Command::new("ffmpeg").arg("-version").output() {
@@ -17,7 +23,32 @@ pub enum ProgressInfo {
     Percentage(f64),
 }
 
-fn run_ffmpeg_with_progress<F>(mut command: Command, mut callback: F) -> Result<()>
+fn run_ffmpeg_with_progress<F>(
+    command: Command,
+    expected_duration: Option<f64>,
+    callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    run_ffmpeg(command, expected_duration, None, callback)
+}
+
+/// Like `run_ffmpeg_with_progress`, but measures progress by the `frame=`
+/// counter. Used for image sequences, where there is no Duration to parse.
+fn run_ffmpeg_with_frame_progress<F>(command: Command, total_frames: u64, callback: F) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    run_ffmpeg(command, None, Some(total_frames), callback)
+}
+
+fn run_ffmpeg<F>(
+    mut command: Command,
+    expected_duration: Option<f64>,
+    expected_frames: Option<u64>,
+    mut callback: F,
+) -> Result<()>
 where
     F: FnMut(ProgressInfo),
 {
@@ -33,8 +64,12 @@ where
         let duration_regex = Regex::new(r"Duration: (\d+):(\d+):(\d+(?:\.\d+)?)").unwrap();
         // Match time=00:00:00.00
         let time_regex = Regex::new(r"time=(\d+):(\d+):(\d+(?:\.\d+)?)").unwrap();
+        // Match frame=  123
+        let frame_regex = Regex::new(r"frame=\s*(\d+)").unwrap();
 
-        let mut total_duration_secs = 0.0;
+        // When the caller knows the output length (e.g. a trimmed range), use it
+        // instead of the input Duration that ffmpeg prints.
+        let mut total_duration_secs = expected_duration.unwrap_or(0.0);
         let mut buf = Vec::new();
         let mut byte = [0u8; 1];
 
@@ -57,14 +92,22 @@ where
                                 callback(ProgressInfo::Log(line.clone()));
                             }
 
-                            if let Some(caps) = duration_regex.captures(&line) {
-                                let h: f64 = caps[1].parse().unwrap_or(0.0);
-                                let m: f64 = caps[2].parse().unwrap_or(0.0);
-                                let s: f64 = caps[3].parse().unwrap_or(0.0);
-                                total_duration_secs = h * 3600.0 + m * 60.0 + s;
+                            if expected_duration.is_none() {
+                                if let Some(caps) = duration_regex.captures(&line) {
+                                    let h: f64 = caps[1].parse().unwrap_or(0.0);
+                                    let m: f64 = caps[2].parse().unwrap_or(0.0);
+                                    let s: f64 = caps[3].parse().unwrap_or(0.0);
+                                    total_duration_secs = h * 3600.0 + m * 60.0 + s;
+                                }
                             }
 
-                            if total_duration_secs > 0.0 {
+                            if let Some(total_frames) = expected_frames.filter(|f| *f > 0) {
+                                if let Some(caps) = frame_regex.captures(&line) {
+                                    let frame: f64 = caps[1].parse().unwrap_or(0.0);
+                                    let percentage = (frame / total_frames as f64).min(1.0);
+                                    callback(ProgressInfo::Percentage(percentage));
+                                }
+                            } else if total_duration_secs > 0.0 {
                                 if let Some(caps) = time_regex.captures(&line) {
                                     let h: f64 = caps[1].parse().unwrap_or(0.0);
                                     let m: f64 = caps[2].parse().unwrap_or(0.0);
@@ -97,24 +140,373 @@ where
     Ok(())
 }
 
+/// The canvas every clip is scaled, padded and resampled to before joining.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CombineTarget {
+    /// Match the first input.
+    First,
+    /// Match the input with the largest frame (and the highest fps/sample rate).
+    Largest,
+    /// An explicit `WxH@fps` canvas.
+    Explicit { width: u32, height: u32, fps: f64 },
+}
+
+impl FromStr for CombineTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "first" => Ok(CombineTarget::First),
+            "largest" => Ok(CombineTarget::Largest),
+            other => {
+                let err = || anyhow!("Invalid target '{}': use first, largest or WxH@fps", s);
+                let (size, fps) = other.split_once('@').ok_or_else(err)?;
+                let (w, h) = size.split_once('x').ok_or_else(err)?;
+                let width: u32 = w.parse().map_err(|_| err())?;
+                let height: u32 = h.parse().map_err(|_| err())?;
+                let fps: f64 = fps.parse().map_err(|_| err())?;
+                if width == 0 || height == 0 || fps <= 0.0 {
+                    return Err(err());
+                }
+                Ok(CombineTarget::Explicit { width, height, fps })
+            }
+        }
+    }
+}
+
+/// How `combine_videos` joins its inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombineMode {
+    /// Stream-copy when every input matches, otherwise re-encode.
+    Auto,
+    /// Always use the concat demuxer with `-c copy`.
+    Copy,
+    /// Always re-encode through the concat filter.
+    Reencode,
+}
+
+impl FromStr for CombineMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(CombineMode::Auto),
+            "copy" => Ok(CombineMode::Copy),
+            "reencode" | "re-encode" => Ok(CombineMode::Reencode),
+            _ => Err(anyhow!("Invalid mode '{}': use auto, copy or reencode", s)),
+        }
+    }
+}
+
+/// xfade transitions offered for joins between combined clips.
+pub const TRANSITIONS: &[&str] = &[
+    "fade",
+    "fadeblack",
+    "fadewhite",
+    "dissolve",
+    "wipeleft",
+    "wiperight",
+    "wipeup",
+    "wipedown",
+    "slideleft",
+    "slideright",
+    "slideup",
+    "slidedown",
+    "smoothleft",
+    "smoothright",
+    "circleopen",
+    "circleclose",
+    "radial",
+    "pixelize",
+];
+
+/// A transition between two clips: an xfade type for video (audio always
+/// uses `acrossfade`) and its length in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub kind: String,
+    pub duration: f64,
+}
+
+/// Parses a comma-separated transition list such as `fade`, or
+/// `fade,wipeleft:0.5,none` for one entry per join. Each entry is a
+/// transition name with an optional `:seconds` override of
+/// `default_duration`; `none` or `cut` means a hard cut.
+pub fn parse_transitions(spec: &str, default_duration: f64) -> Result<Vec<Option<Transition>>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (kind, duration) = match item.split_once(':') {
+                Some((kind, d)) => (kind, parse_timestamp(d)?),
+                None => (item, default_duration),
+            };
+            let kind = kind.to_ascii_lowercase();
+            if kind == "none" || kind == "cut" {
+                return Ok(None);
+            }
+            if !TRANSITIONS.contains(&kind.as_str()) {
+                return Err(anyhow!(
+                    "Unknown transition '{}'. Available: none, {}",
+                    kind,
+                    TRANSITIONS.join(", ")
+                ));
+            }
+            if duration <= 0.0 {
+                return Err(anyhow!("Transition duration must be greater than zero"));
+            }
+            Ok(Some(Transition { kind, duration }))
+        })
+        .collect()
+}
+
+/// Expands a parsed transition list to exactly one entry per join: none
+/// means hard cuts everywhere and a single entry applies to every join.
+fn transitions_per_join(
+    transitions: &[Option<Transition>],
+    joins: usize,
+) -> Result<Vec<Option<Transition>>> {
+    match transitions.len() {
+        0 => Ok(vec![None; joins]),
+        1 => Ok(vec![transitions[0].clone(); joins]),
+        n if n == joins => Ok(transitions.to_vec()),
+        n => Err(anyhow!(
+            "Got {} transitions for {} joins; give one for all joins or one per join",
+            n,
+            joins
+        )),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CombineOptions {
+    pub target: CombineTarget,
+    pub mode: CombineMode,
+    /// Empty for hard cuts everywhere, a single entry applied to every join,
+    /// or exactly one entry per join.
+    pub transitions: Vec<Option<Transition>>,
+}
+
+impl Default for CombineOptions {
+    fn default() -> Self {
+        Self {
+            target: CombineTarget::First,
+            mode: CombineMode::Auto,
+            transitions: Vec::new(),
+        }
+    }
+}
+
+/// Whether every input has the same codecs, resolution, timebase and audio
+/// layout, so the concat demuxer can join them without re-encoding.
+fn streams_match(infos: &[MediaInfo]) -> bool {
+    let signature = |info: &MediaInfo| {
+        let video = info.video().map(|v| {
+            (
+                v.codec.clone(),
+                v.width,
+                v.height,
+                v.pix_fmt.clone(),
+                v.time_base.clone(),
+            )
+        });
+        let audio = info.audio().map(|a| {
+            (
+                a.codec.clone(),
+                a.sample_rate,
+                a.channels,
+                a.channel_layout.clone(),
+            )
+        });
+        (video, audio)
+    };
+
+    let first = signature(&infos[0]);
+    first.0.is_some() && infos[1..].iter().all(|info| signature(info) == first)
+}
+
+/// Resolved output canvas for a combine.
+struct Canvas {
+    width: u32,
+    height: u32,
+    fps: f64,
+    sample_rate: u32,
+}
+
+fn resolve_canvas(target: CombineTarget, infos: &[MediaInfo]) -> Result<Canvas> {
+    const DEFAULT_FPS: f64 = 30.0;
+    const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+    let sample_rates = infos
+        .iter()
+        .filter_map(|i| i.audio().and_then(|a| a.sample_rate));
+
+    let (width, height, fps, sample_rate) = match target {
+        CombineTarget::Explicit { width, height, fps } => (
+            width,
+            height,
+            fps,
+            sample_rates.max().unwrap_or(DEFAULT_SAMPLE_RATE),
+        ),
+        CombineTarget::First => {
+            let first = &infos[0];
+            let video = first
+                .video()
+                .ok_or_else(|| anyhow!("{} has no video stream", first.path))?;
+            (
+                video.width.unwrap_or(0),
+                video.height.unwrap_or(0),
+                video.fps.unwrap_or(DEFAULT_FPS),
+                first
+                    .audio()
+                    .and_then(|a| a.sample_rate)
+                    .or_else(|| sample_rates.max())
+                    .unwrap_or(DEFAULT_SAMPLE_RATE),
+            )
+        }
+        CombineTarget::Largest => {
+            let largest = infos
+                .iter()
+                .filter_map(|i| i.video())
+                .max_by_key(|v| v.width.unwrap_or(0) as u64 * v.height.unwrap_or(0) as u64)
+                .ok_or_else(|| anyhow!("None of the inputs has a video stream"))?;
+            let fps = infos
+                .iter()
+                .filter_map(|i| i.video().and_then(|v| v.fps))
+                .reduce(f64::max)
+                .unwrap_or(DEFAULT_FPS);
+            (
+                largest.width.unwrap_or(0),
+                largest.height.unwrap_or(0),
+                fps,
+                sample_rates.max().unwrap_or(DEFAULT_SAMPLE_RATE),
+            )
+        }
+    };
+
+    if width == 0 || height == 0 {
+        return Err(anyhow!("Could not determine the output resolution"));
+    }
+
+    // yuv420p needs even dimensions.
+    Ok(Canvas {
+        width: width + width % 2,
+        height: height + height % 2,
+        fps,
+        sample_rate,
+    })
+}
+
+/// Bring every clip onto the same canvas, frame rate, pixel format and
+/// sample rate, labelling them `[v{i}]`/`[a{i}]`; concat refuses (or produces
+/// garbage) otherwise. With `has_audio`, clips without a track get silence.
+fn write_clip_filters(
+    filter: &mut String,
+    infos: &[MediaInfo],
+    canvas: &Canvas,
+    has_audio: bool,
+) -> Result<()> {
+    for (i, info) in infos.iter().enumerate() {
+        write!(
+            filter,
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,\
+             pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];",
+            i = i,
+            w = canvas.width,
+            h = canvas.height,
+            fps = canvas.fps
+        )
+        .unwrap();
+        if !has_audio {
+            continue;
+        }
+        // Pad/trim audio to the clip length so later clips don't drift.
+        if info.has_audio() {
+            write!(
+                filter,
+                "[{i}:a]aresample={rate},aformat=sample_fmts=fltp:channel_layouts=stereo,\
+                 apad,atrim=duration={dur}[a{i}];",
+                i = i,
+                rate = canvas.sample_rate,
+                dur = info.duration_secs()?
+            )
+            .unwrap();
+        } else {
+            write!(
+                filter,
+                "anullsrc=r={rate}:cl=stereo,atrim=duration={dur},\
+                 aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}];",
+                i = i,
+                rate = canvas.sample_rate,
+                dur = info.duration_secs()?
+            )
+            .unwrap();
+        }
+    }
+    Ok(())
+}
+
 pub fn combine_videos<F>(
     inputs: &[std::path::PathBuf],
     output: &Path,
+    options: &CombineOptions,
     mut callback: F,
 ) -> Result<()>
 where
     F: FnMut(ProgressInfo),
 {
-    callback(ProgressInfo::Log(
-        "Combining videos using ffmpeg filter...".to_string(),
-    ));
-
-    let has_audio = if let Some(first) = inputs.first() {
-        probe_has_audio(first)?
-    } else {
+    if inputs.is_empty() {
         return Err(anyhow!("No input files provided"));
+    }
+
+    let infos = inputs
+        .iter()
+        .map(|input| probe::probe(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    let transitions = transitions_per_join(&options.transitions, inputs.len() - 1)?;
+    let has_transitions = transitions.iter().any(Option::is_some);
+
+    let total_duration: f64 = infos.iter().filter_map(|i| i.duration).sum();
+    let mut expected = (total_duration > 0.0).then_some(total_duration);
+
+    let use_copy = match options.mode {
+        CombineMode::Copy if has_transitions => {
+            return Err(anyhow!(
+                "Transitions require re-encoding; they can't be used with copy mode"
+            ));
+        }
+        CombineMode::Copy => {
+            if !streams_match(&infos) {
+                callback(ProgressInfo::Log(
+                    "Warning: inputs differ in codec, resolution or audio layout; \
+                     stream copy may produce a broken file."
+                        .to_string(),
+                ));
+            }
+            true
+        }
+        CombineMode::Reencode => false,
+        CombineMode::Auto => !has_transitions && streams_match(&infos),
     };
 
+    if use_copy {
+        return combine_with_demuxer(inputs, output, expected, callback);
+    }
+
+    // If any clip has audio the output gets a continuous track; silent clips
+    // are padded with generated silence of their own length.
+    let has_audio = infos.iter().any(|i| i.has_audio());
+    let canvas = resolve_canvas(options.target, &infos)?;
+
+    callback(ProgressInfo::Log(format!(
+        "Combining {} videos at {}x{} @ {:.3} fps using ffmpeg filter...",
+        inputs.len(),
+        canvas.width,
+        canvas.height,
+        canvas.fps
+    )));
+
     let mut command = Command::new("ffmpeg");
 
     for input in inputs {
@@ -122,20 +514,34 @@ where
     }
 
     let mut filter = String::new();
-    for i in 0..inputs.len() {
-        use std::fmt::Write;
+    write_clip_filters(&mut filter, &infos, &canvas, has_audio)?;
+    if has_transitions {
+        let names: Vec<&str> = infos.iter().map(|i| i.path.as_str()).collect();
+        let lengths = infos
+            .iter()
+            .map(MediaInfo::duration_secs)
+            .collect::<Result<Vec<_>>>()?;
+        expected = Some(write_transition_chain(
+            &mut filter,
+            &names,
+            &lengths,
+            &transitions,
+            has_audio,
+        )?);
+    } else {
+        for i in 0..inputs.len() {
+            if has_audio {
+                write!(filter, "[v{}][a{}]", i, i).unwrap();
+            } else {
+                write!(filter, "[v{}]", i).unwrap();
+            }
+        }
         if has_audio {
-            write!(filter, "[{}:v][{}:a]", i, i).unwrap();
+            write!(filter, "concat=n={}:v=1:a=1[outv][outa]", inputs.len()).unwrap();
         } else {
-            write!(filter, "[{}:v]", i).unwrap();
+            write!(filter, "concat=n={}:v=1:a=0[outv]", inputs.len()).unwrap();
         }
     }
-    use std::fmt::Write;
-    if has_audio {
-        write!(filter, "concat=n={}:v=1:a=1[outv][outa]", inputs.len()).unwrap();
-    } else {
-        write!(filter, "concat=n={}:v=1:a=0[outv]", inputs.len()).unwrap();
-    }
 
     command
         .arg("-filter_complex")
@@ -149,136 +555,4416 @@ where
 
     command.arg("-y").arg(output);
 
-    run_ffmpeg_with_progress(command, callback)
+    run_ffmpeg_with_progress(command, expected, callback)
+}
+
+/// Joins the normalized `[v{i}]`/`[a{i}]` streams pairwise, using xfade and
+/// acrossfade where a transition is set and a two-way concat for hard cuts.
+/// Each xfade offset is the running output length minus the transition, so
+/// offsets follow the clip `lengths`. Returns the output duration.
+fn write_transition_chain(
+    filter: &mut String,
+    names: &[&str],
+    lengths: &[f64],
+    transitions: &[Option<Transition>],
+    has_audio: bool,
+) -> Result<f64> {
+    let mut length = lengths[0];
+    let mut video = "v0".to_string();
+    let mut audio = "a0".to_string();
+
+    for (j, transition) in transitions.iter().enumerate() {
+        let next = j + 1;
+        let next_length = lengths[next];
+        let last = next == transitions.len();
+        let out_video = if last {
+            "outv".to_string()
+        } else {
+            format!("vx{}", next)
+        };
+        let out_audio = if last {
+            "outa".to_string()
+        } else {
+            format!("ax{}", next)
+        };
+
+        match transition {
+            Some(t) => {
+                if t.duration >= length || t.duration >= next_length {
+                    return Err(anyhow!(
+                        "Transition of {}s is longer than the clips it joins ({} and {})",
+                        t.duration,
+                        names[j],
+                        names[next]
+                    ));
+                }
+                write!(
+                    filter,
+                    "[{}][v{}]xfade=transition={}:duration={}:offset={}[{}];",
+                    video,
+                    next,
+                    t.kind,
+                    t.duration,
+                    length - t.duration,
+                    out_video
+                )
+                .unwrap();
+                if has_audio {
+                    write!(
+                        filter,
+                        "[{}][a{}]acrossfade=d={}[{}];",
+                        audio, next, t.duration, out_audio
+                    )
+                    .unwrap();
+                }
+                length += next_length - t.duration;
+            }
+            None => {
+                write!(
+                    filter,
+                    "[{}][v{}]concat=n=2:v=1:a=0[{}];",
+                    video, next, out_video
+                )
+                .unwrap();
+                if has_audio {
+                    write!(
+                        filter,
+                        "[{}][a{}]concat=n=2:v=0:a=1[{}];",
+                        audio, next, out_audio
+                    )
+                    .unwrap();
+                }
+                length += next_length;
+            }
+        }
+
+        video = out_video;
+        audio = out_audio;
+    }
+
+    // Every chain above ends in ';', which ffmpeg rejects on the last one.
+    filter.pop();
+    Ok(length)
 }
 
-pub fn compress_video<F>(input: &Path, output: &Path, crf: u8, mut callback: F) -> Result<()>
+/// Joins inputs losslessly with the concat demuxer. Only valid when every
+/// input shares the same stream parameters.
+fn combine_with_demuxer<F>(
+    inputs: &[std::path::PathBuf],
+    output: &Path,
+    expected: Option<f64>,
+    mut callback: F,
+) -> Result<()>
 where
     F: FnMut(ProgressInfo),
 {
-    callback(ProgressInfo::Log("Compressing video...".to_string()));
+    callback(ProgressInfo::Log(format!(
+        "Combining {} videos losslessly using the concat demuxer...",
+        inputs.len()
+    )));
+
+    // The demuxer resolves relative paths against the list file, so write
+    // absolute ones.
+    let mut list = String::new();
+    for input in inputs {
+        let path = input
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", input.display()))?;
+        let path = path.to_string_lossy().replace('\'', "'\\''");
+        writeln!(list, "file '{}'", path).unwrap();
+    }
+
+    let list_path = std::env::temp_dir().join(format!("framix_concat_{}.txt", std::process::id()));
+    std::fs::write(&list_path, list).context("Failed to write concat list")?;
+
     let mut command = Command::new("ffmpeg");
     command
+        .arg("-f")
+        .arg("concat")
+        .arg("-safe")
+        .arg("0")
         .arg("-i")
-        .arg(input)
-        .arg("-vcodec")
-        .arg("libx264")
-        .arg("-crf")
-        .arg(crf.to_string())
+        .arg(&list_path)
+        .arg("-map")
+        .arg("0")
+        .arg("-c")
+        .arg("copy")
         .arg("-y")
         .arg(output);
 
-    run_ffmpeg_with_progress(command, callback)
+    let result = run_ffmpeg_with_progress(command, expected, callback);
+    let _ = std::fs::remove_file(&list_path);
+    result
 }
 
-pub fn add_music<F>(
-    video: &Path,
-    audio: &Path,
-    output: &Path,
-    reduce_original: &str,
+/// Where `split_video` cuts the input.
+pub enum SplitMode {
+    /// A new segment every N seconds.
+    Every(f64),
+    /// Cut at each of the given timestamps (in seconds).
+    At(Vec<f64>),
+    /// Cut at the start of every chapter embedded in the input.
+    Chapters,
+}
+
+/// Splits `input` into numbered segments in a single ffmpeg pass using the
+/// segment muxer, so progress covers the whole split. `output_template` must
+/// contain a printf-style counter such as `part_%03d.mp4`; numbering starts
+/// at 1.
+pub fn split_video<F>(
+    input: &Path,
+    output_template: &str,
+    mode: &SplitMode,
+    accurate: bool,
     mut callback: F,
 ) -> Result<()>
 where
     F: FnMut(ProgressInfo),
 {
-    callback(ProgressInfo::Log("Adding music...".to_string()));
+    validate_counter_pattern(output_template, "part_%03d.mp4")?;
 
-    let has_audio = probe_has_audio(video)?;
+    // (segment muxer option, its value, matching -force_key_frames spec)
+    let (segment_arg, segment_value, keyframes) = match mode {
+        SplitMode::Every(secs) => {
+            if *secs <= 0.0 {
+                return Err(anyhow!("Segment length must be greater than zero"));
+            }
+            callback(ProgressInfo::Log(format!(
+                "Splitting into {}s segments...",
+                secs
+            )));
+            (
+                "-segment_time",
+                secs.to_string(),
+                format!("expr:gte(t,n_forced*{})", secs),
+            )
+        }
+        SplitMode::At(_) | SplitMode::Chapters => {
+            let mut times: Vec<f64> = match mode {
+                SplitMode::At(times) => times.clone(),
+                _ => probe::probe(input)?
+                    .chapters
+                    .iter()
+                    .map(|c| c.start)
+                    .collect(),
+            };
+            times.retain(|t| *t > 0.0);
+            times.sort_by(|a, b| a.total_cmp(b));
+            times.dedup();
+            if times.is_empty() {
+                return Err(match mode {
+                    SplitMode::Chapters => {
+                        anyhow!("{} has no chapter markers", input.display())
+                    }
+                    _ => anyhow!("No cut points after the start of the video"),
+                });
+            }
+            callback(ProgressInfo::Log(format!(
+                "Splitting into {} segments...",
+                times.len() + 1
+            )));
+            let list = times
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            ("-segment_times", list.clone(), list)
+        }
+    };
 
     let mut command = Command::new("ffmpeg");
-    if has_audio {
-        let filter = format!(
-            "[0:a]volume={}[a0];[1:a]volume=1.0[a1];[a0][a1]amix=inputs=2:duration=first[out]",
-            reduce_original
-        );
+    command.arg("-i").arg(input);
+
+    if accurate {
+        // Force keyframes at the cut points so each segment starts exactly
+        // where it was asked to.
         command
-            .arg("-i")
-            .arg(video)
-            .arg("-i")
-            .arg(audio)
-            .arg("-filter_complex")
-            .arg(&filter)
-            .arg("-map")
-            .arg("0:v")
-            .arg("-map")
-            .arg("[out]");
+            .arg("-c:v")
+            .arg("libx264")
+            .arg("-c:a")
+            .arg("aac")
+            .arg("-force_key_frames")
+            .arg(keyframes);
     } else {
-        command
-            .arg("-i")
-            .arg(video)
-            .arg("-i")
-            .arg(audio)
-            .arg("-map")
-            .arg("0:v")
-            .arg("-map")
-            .arg("1:a")
-            .arg("-shortest");
-    };
+        command.arg("-c").arg("copy");
+    }
 
-    command.arg("-y").arg(output);
+    command
+        .arg("-f")
+        .arg("segment")
+        .arg(segment_arg)
+        .arg(segment_value);
+
+    command
+        .arg("-segment_start_number")
+        .arg("1")
+        .arg("-reset_timestamps")
+        .arg("1")
+        .arg("-y")
+        .arg(output_template);
 
-    run_ffmpeg_with_progress(command, callback)
+    run_ffmpeg_with_progress(command, None, callback)
 }
 
-fn probe_has_audio(path: &Path) -> Result<bool> {
-    let output = Command::new("ffprobe")
-        .arg("-v")
-        .arg("error")
-        .arg("-select_streams")
-        .arg("a")
-        .arg("-show_entries")
-        .arg("stream=codec_type")
-        .arg("-of")
-        .arg("csv=p=0")
-        .arg(path)
-        .output()
-        .context("Failed to run ffprobe")?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
 
-    if !output.status.success() {
-        return Err(anyhow!("ffprobe failed"));
+impl FromStr for VideoCodec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "h264" | "x264" | "avc" => Ok(VideoCodec::H264),
+            "h265" | "x265" | "hevc" => Ok(VideoCodec::H265),
+            "vp9" => Ok(VideoCodec::Vp9),
+            "av1" => Ok(VideoCodec::Av1),
+            _ => Err(anyhow!("Invalid codec '{}': use h264, h265, vp9 or av1", s)),
+        }
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(!stdout.trim().is_empty())
+impl VideoCodec {
+    fn name(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::H265 => "H.265",
+            VideoCodec::Vp9 => "VP9",
+            VideoCodec::Av1 => "AV1",
+        }
+    }
+
+    /// The ffmpeg encoder to use, checked against the local build. AV1
+    /// prefers SVT-AV1 and falls back to libaom.
+    fn encoder(self) -> Result<&'static str> {
+        let candidates: &[&'static str] = match self {
+            VideoCodec::H264 => &["libx264"],
+            VideoCodec::H265 => &["libx265"],
+            VideoCodec::Vp9 => &["libvpx-vp9"],
+            VideoCodec::Av1 => &["libsvtav1", "libaom-av1"],
+        };
+        let available = available_encoders()?;
+        candidates
+            .iter()
+            .find(|c| available.iter().any(|a| a == *c))
+            .copied()
+            .ok_or_else(|| {
+                anyhow!(
+                    "Your ffmpeg build has no {} encoder (needs {})",
+                    self.name(),
+                    candidates.join(" or ")
+                )
+            })
+    }
 }
 
-pub fn get_info<F>(input: &Path, mut callback: F) -> Result<()>
-where
-    F: FnMut(ProgressInfo),
-{
-    let output = Command::new("ffprobe")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Mp3,
+    Copy,
+    None,
+}
+
+impl FromStr for AudioCodec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "aac" => Ok(AudioCodec::Aac),
+            "opus" => Ok(AudioCodec::Opus),
+            "mp3" => Ok(AudioCodec::Mp3),
+            "copy" => Ok(AudioCodec::Copy),
+            "none" => Ok(AudioCodec::None),
+            _ => Err(anyhow!(
+                "Invalid audio codec '{}': use aac, opus, mp3, copy or none",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressOptions {
+    pub crf: u8,
+    /// Aim for this output size in bytes with a two-pass encode instead of
+    /// using `crf`.
+    pub target_size: Option<u64>,
+    pub codec: VideoCodec,
+    /// Encoder speed preset (`slow`, `veryfast`... for x264/x265, a number
+    /// for SVT-AV1, `-cpu-used` for VP9 and libaom).
+    pub preset: Option<String>,
+    pub tune: Option<String>,
+    pub pix_fmt: Option<String>,
+    /// `None` picks the natural codec for the output container.
+    pub audio_codec: Option<AudioCodec>,
+    /// Audio bitrate in kb/s.
+    pub audio_bitrate: u32,
+    /// Downscale (never upscale) to fit within these bounds, keeping the
+    /// aspect ratio.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Drop frames down to this rate if the source is faster.
+    pub max_fps: Option<f64>,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        Self {
+            crf: 23,
+            target_size: None,
+            codec: VideoCodec::H264,
+            preset: None,
+            tune: None,
+            pix_fmt: None,
+            audio_codec: None,
+            audio_bitrate: 128,
+            max_width: None,
+            max_height: None,
+            max_fps: None,
+        }
+    }
+}
+
+fn available_encoders() -> Result<Vec<String>> {
+    let output = Command::new("ffmpeg")
         .arg("-hide_banner")
-        .arg("-i")
-        .arg(input)
+        .arg("-encoders")
         .output()
-        .context("Failed to execute ffprobe")?;
+        .context("Failed to list ffmpeg encoders")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Lines look like " V....D libx264   libx264 H.264 / AVC ..."
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Checks that the output container can hold the chosen codecs and fills in
+/// the default audio codec for it. `source_audio` is the probed codec of the
+/// input's audio, which is what `AudioCodec::Copy` puts in the container.
+/// Containers Framix doesn't know (.avi, .ts, .flv, ...) are left to ffmpeg.
+fn resolve_container(
+    output: &Path,
+    codec: VideoCodec,
+    audio: Option<AudioCodec>,
+    source_audio: Option<&str>,
+) -> Result<AudioCodec> {
+    let ext = lowercase_extension(output);
 
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    callback(ProgressInfo::Log(stderr.to_string()));
+    let (videos, audios): (&[VideoCodec], &[AudioCodec]) = match ext.as_str() {
+        "mp4" | "m4v" => (
+            &[
+                VideoCodec::H264,
+                VideoCodec::H265,
+                VideoCodec::Vp9,
+                VideoCodec::Av1,
+            ],
+            &[AudioCodec::Aac, AudioCodec::Mp3, AudioCodec::Opus],
+        ),
+        "mov" => (
+            &[VideoCodec::H264, VideoCodec::H265],
+            &[AudioCodec::Aac, AudioCodec::Mp3],
+        ),
+        "mkv" => (
+            &[
+                VideoCodec::H264,
+                VideoCodec::H265,
+                VideoCodec::Vp9,
+                VideoCodec::Av1,
+            ],
+            &[AudioCodec::Aac, AudioCodec::Opus, AudioCodec::Mp3],
+        ),
+        "webm" => (&[VideoCodec::Vp9, VideoCodec::Av1], &[AudioCodec::Opus]),
+        _ => return Ok(audio.unwrap_or(AudioCodec::Aac)),
+    };
 
-    if !output.status.success() {
-        return Err(anyhow!("ffprobe failed"));
+    if !videos.contains(&codec) {
+        return Err(anyhow!(
+            "A .{} file can't hold {} video; use {}",
+            ext,
+            codec.name(),
+            videos
+                .iter()
+                .map(|c| c.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
     }
-    Ok(())
+
+    let audio = audio.unwrap_or(audios[0]);
+    if audio == AudioCodec::Copy {
+        if let Some(source) = source_audio {
+            if !container_accepts_audio(output, source) {
+                return Err(anyhow!(
+                    "A .{} file can't hold the source's {} audio; pick an audio codec instead of copy",
+                    ext,
+                    source
+                ));
+            }
+        }
+    } else if audio != AudioCodec::None && !audios.contains(&audio) {
+        return Err(anyhow!(
+            "A .{} file can't hold {:?} audio; use {}",
+            ext,
+            audio,
+            audios
+                .iter()
+                .map(|c| format!("{:?}", c).to_ascii_lowercase())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    Ok(audio)
+}
+
+/// Fits `width`x`height` inside the optional bounds, keeping the aspect ratio
+/// and even dimensions. Never upscales.
+pub fn capped_dimensions(
+    width: u32,
+    height: u32,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+) -> (u32, u32) {
+    let scale_w = max_width.map_or(1.0, |m| m as f64 / width as f64);
+    let scale_h = max_height.map_or(1.0, |m| m as f64 / height as f64);
+    let scale = scale_w.min(scale_h);
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    let even = |v: f64| ((v / 2.0).floor() as u32 * 2).max(2);
+    (even(width as f64 * scale), even(height as f64 * scale))
+}
+
+/// Parses a size bound such as `1920x1080`, `x720` or `1280x` (either side
+/// may be left empty) into optional width and height limits.
+pub fn parse_max_size(value: &str) -> Result<(Option<u32>, Option<u32>)> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok((None, None));
+    }
+    let err = || anyhow!("Invalid size '{}': use WxH, Wx or xH", value);
+    let (w, h) = value.split_once('x').ok_or_else(err)?;
+    let parse = |v: &str| -> Result<Option<u32>> {
+        let v = v.trim();
+        match v {
+            "" => Ok(None),
+            v => match v.parse() {
+                Ok(0) | Err(_) => Err(err()),
+                Ok(n) => Ok(Some(n)),
+            },
+        }
+    };
+    Ok((parse(w)?, parse(h)?))
+}
+
+/// Parses a frame rate cap; an empty value means no cap.
+pub fn parse_max_fps(value: &str) -> Result<Option<f64>> {
+    match value.trim() {
+        "" => Ok(None),
+        v => match v.parse::<f64>() {
+            Ok(fps) if fps > 0.0 && fps.is_finite() => Ok(Some(fps)),
+            _ => Err(anyhow!("Invalid max fps '{}': use a number above zero", v)),
+        },
+    }
+}
+
+/// The output frame rate when capping at `max_fps`, or `None` to keep the
+/// source rate.
+pub fn capped_fps(fps: Option<f64>, max_fps: Option<f64>) -> Option<f64> {
+    match (fps, max_fps) {
+        (Some(fps), Some(max)) if fps > max => Some(max),
+        _ => None,
+    }
+}
+
+/// The `-vf` chain applying `max_width`/`max_height`/`max_fps`, if any.
+fn compress_filter(info: &MediaInfo, options: &CompressOptions) -> Option<String> {
+    let mut filters = Vec::new();
+
+    if let Some((w, h)) = info.video().and_then(|v| v.display_size()) {
+        let (tw, th) = capped_dimensions(w, h, options.max_width, options.max_height);
+        if (tw, th) != (w, h) {
+            filters.push(format!("scale={}:{}", tw, th));
+        }
+    }
+
+    if let Some(fps) = capped_fps(info.video().and_then(|v| v.fps), options.max_fps) {
+        filters.push(format!("fps={}", fps));
+    }
+
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+/// Preset, tune and pixel-format flags in the dialect of each encoder.
+fn video_encoder_args(encoder: &str, options: &CompressOptions) -> Result<Vec<String>> {
+    let mut args = vec!["-c:v".to_string(), encoder.to_string()];
+
+    if let Some(preset) = &options.preset {
+        match encoder {
+            "libx264" | "libx265" | "libsvtav1" => {
+                args.extend(["-preset".to_string(), preset.clone()])
+            }
+            "libvpx-vp9" | "libaom-av1" => args.extend(["-cpu-used".to_string(), preset.clone()]),
+            _ => {}
+        }
+    }
+
+    if let Some(tune) = &options.tune {
+        match encoder {
+            "libx264" | "libx265" | "libaom-av1" => {
+                args.extend(["-tune".to_string(), tune.clone()])
+            }
+            _ => return Err(anyhow!("{} does not support --tune", encoder)),
+        }
+    }
+
+    if let Some(pix_fmt) = &options.pix_fmt {
+        args.extend(["-pix_fmt".to_string(), pix_fmt.clone()]);
+    }
+
+    // Apple players only recognise HEVC in MP4/MOV with the hvc1 tag.
+    if encoder == "libx265" {
+        args.extend(["-tag:v".to_string(), "hvc1".to_string()]);
+    }
+
+    Ok(args)
+}
+
+fn audio_encoder_args(codec: AudioCodec, bitrate_kbps: u32) -> Vec<String> {
+    let encoder = match codec {
+        AudioCodec::Aac => "aac",
+        AudioCodec::Opus => "libopus",
+        AudioCodec::Mp3 => "libmp3lame",
+        AudioCodec::Copy => return vec!["-c:a".to_string(), "copy".to_string()],
+        AudioCodec::None => return vec!["-an".to_string()],
+    };
+    vec![
+        "-c:a".to_string(),
+        encoder.to_string(),
+        "-b:a".to_string(),
+        format!("{}k", bitrate_kbps),
+    ]
+}
+
+/// Parses a size such as `25MB`, `800k`, `1.5G` or `100MiB` into bytes.
+/// A bare number is taken as megabytes.
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size: '{}'", value))?;
+
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "m" | "mb" => 1_000_000.0,
+        "b" => 1.0,
+        "k" | "kb" => 1_000.0,
+        "g" | "gb" => 1_000_000_000.0,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(anyhow!("Invalid size unit in '{}'", value)),
+    };
+
+    let bytes = (number * multiplier) as u64;
+    if bytes == 0 {
+        return Err(anyhow!("Size must be greater than zero"));
+    }
+    Ok(bytes)
 }
 
-pub fn timelapse<F>(input: &Path, output: &Path, speed: f64, mut callback: F) -> Result<()>
+fn format_size(bytes: u64) -> String {
+    format!("{:.2} MB", bytes as f64 / 1_000_000.0)
+}
+
+/// Maps a sub-step's 0..1 progress onto `start..start + span` of the whole
+/// operation, so multi-pass commands report one continuous bar.
+fn progress_slice<F>(callback: &mut F, start: f64, span: f64) -> impl FnMut(ProgressInfo) + '_
 where
     F: FnMut(ProgressInfo),
 {
-    callback(ProgressInfo::Log("Creating timelapse...".to_string()));
+    move |info| match info {
+        ProgressInfo::Percentage(p) => callback(ProgressInfo::Percentage(start + p * span)),
+        other => callback(other),
+    }
+}
 
-    let filter = format!("setpts=PTS/{}", speed);
+/// The extension of `path` in lower case, or an empty string without one.
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
 
-    let mut command = Command::new("ffmpeg");
-    command
-        .arg("-i")
-        .arg(input)
-        .arg("-filter:v")
-        .arg(&filter)
-        .arg("-an")
-        .arg("-y")
-        .arg(output);
+fn has_extension(path: &Path, ext: &str) -> bool {
+    lowercase_extension(path) == ext.to_ascii_lowercase()
+}
+
+/// Matches a printf-style counter such as `%d` or `%03d` in an output
+/// template; the groups hold the zero flag and the width.
+fn counter_regex() -> &'static Regex {
+    static COUNTER: OnceLock<Regex> = OnceLock::new();
+    COUNTER.get_or_init(|| Regex::new(r"%(0?)(\d*)d").unwrap())
+}
 
-    run_ffmpeg_with_progress(command, callback)
+/// Checks that `template` holds a counter for numbering several outputs;
+/// `example` shows a valid template in the error.
+fn validate_counter_pattern(template: &str, example: &str) -> Result<()> {
+    if counter_regex().is_match(template) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Output template must contain a counter like %d or %03d, e.g. {}",
+            example
+        ))
+    }
+}
+
+fn null_output() -> &'static str {
+    if cfg!(windows) {
+        "NUL"
+    } else {
+        "/dev/null"
+    }
+}
+
+pub fn compress_video<F>(
+    input: &Path,
+    output: &Path,
+    options: &CompressOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    if options.max_width == Some(0) || options.max_height == Some(0) {
+        return Err(anyhow!("Max width and height must be greater than zero"));
+    }
+    if options
+        .max_fps
+        .is_some_and(|fps| fps <= 0.0 || !fps.is_finite())
+    {
+        return Err(anyhow!("Max fps must be greater than zero"));
+    }
+
+    callback(ProgressInfo::Log("Compressing video...".to_string()));
+
+    let info = probe::probe(input)?;
+    if info.video().is_none() {
+        return Err(anyhow!("{} has no video stream", input.display()));
+    }
+
+    let source_audio = info.audio().and_then(|a| a.codec.as_deref());
+    let mut audio_codec =
+        resolve_container(output, options.codec, options.audio_codec, source_audio)?;
+    if !info.has_audio() {
+        audio_codec = AudioCodec::None;
+    }
+    let encoder = options.codec.encoder()?;
+
+    callback(ProgressInfo::Log(format!(
+        "Encoding with {} ({:?} audio)",
+        encoder, audio_codec
+    )));
+    if let Some(video) = info.video() {
+        if let Some((w, h)) = video.display_size() {
+            let (tw, th) = capped_dimensions(w, h, options.max_width, options.max_height);
+            let fps = capped_fps(video.fps, options.max_fps).or(video.fps);
+            callback(ProgressInfo::Log(format!(
+                "Source {}x{} @ {:.2} fps -> target {}x{} @ {:.2} fps",
+                w,
+                h,
+                video.fps.unwrap_or(0.0),
+                tw,
+                th,
+                fps.unwrap_or(0.0)
+            )));
+        }
+    }
+
+    if options.target_size.is_some() {
+        return compress_to_size(
+            input,
+            output,
+            &info,
+            options,
+            encoder,
+            audio_codec,
+            callback,
+        );
+    }
+
+    let video_args = video_encoder_args(encoder, options)?;
+    let audio_args = audio_encoder_args(audio_codec, options.audio_bitrate);
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-i").arg(input);
+    if let Some(filter) = compress_filter(&info, options) {
+        command.arg("-vf").arg(filter);
+    }
+    command
+        .args(&video_args)
+        .arg("-crf")
+        .arg(options.crf.to_string());
+
+    // VP9 and libaom only honour CRF as constant quality with a zero bitrate.
+    if matches!(encoder, "libvpx-vp9" | "libaom-av1") {
+        command.arg("-b:v").arg("0");
+    }
+
+    command.args(&audio_args).arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, info.duration, callback)
+}
+
+/// Two-pass encode whose video bitrate is derived from the target size, the
+/// probed duration and the audio bitrate.
+fn compress_to_size<F>(
+    input: &Path,
+    output: &Path,
+    info: &MediaInfo,
+    options: &CompressOptions,
+    encoder: &str,
+    audio_codec: AudioCodec,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    // Leave room for container overhead so the result lands under the target.
+    const MUXING_OVERHEAD: f64 = 0.98;
+    const MIN_VIDEO_BITRATE: f64 = 50_000.0;
+
+    let Some(target) = options.target_size else {
+        return Err(anyhow!("No target size given"));
+    };
+    let video_args = video_encoder_args(encoder, options)?;
+    let audio_args = audio_encoder_args(audio_codec, options.audio_bitrate);
+    let audio_bitrate_kbps = options.audio_bitrate;
+    let filter = compress_filter(info, options);
+
+    let duration = info.duration_secs()?;
+    let audio_bitrate = match audio_codec {
+        AudioCodec::None => 0,
+        AudioCodec::Copy => info
+            .audio()
+            .and_then(|a| a.bit_rate)
+            .unwrap_or(audio_bitrate_kbps as u64 * 1000),
+        _ => audio_bitrate_kbps as u64 * 1000,
+    };
+    let total_bitrate = target as f64 * 8.0 * MUXING_OVERHEAD / duration;
+    let video_bitrate = total_bitrate - audio_bitrate as f64;
+
+    if video_bitrate < MIN_VIDEO_BITRATE {
+        return Err(anyhow!(
+            "Target size {} is too small for a {:.0}s video",
+            format_size(target),
+            duration
+        ));
+    }
+    let video_bitrate = video_bitrate as u64;
+
+    callback(ProgressInfo::Log(format!(
+        "Two-pass encode for {}: video {} kb/s, audio {} kb/s",
+        format_size(target),
+        video_bitrate / 1000,
+        audio_bitrate / 1000
+    )));
+
+    let passlog = std::env::temp_dir().join(format!("framix_2pass_{}", std::process::id()));
+
+    let build_pass = |pass: u8| -> Result<Command> {
+        let mut command = Command::new("ffmpeg");
+        command.arg("-i").arg(input);
+        if let Some(filter) = &filter {
+            command.arg("-vf").arg(filter);
+        }
+        command
+            .args(&video_args)
+            .arg("-b:v")
+            .arg(video_bitrate.to_string());
+        match encoder {
+            "libx264" | "libvpx-vp9" | "libaom-av1" => {
+                command
+                    .arg("-pass")
+                    .arg(pass.to_string())
+                    .arg("-passlogfile")
+                    .arg(&passlog);
+            }
+            "libx265" => {
+                command.arg("-x265-params").arg(format!(
+                    "pass={}:stats={}.log",
+                    pass,
+                    passlog.display()
+                ));
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Target size is not supported with {}; use --crf instead",
+                    encoder
+                ))
+            }
+        }
+        Ok(command)
+    };
+
+    let mut first = build_pass(1)?;
+    first
+        .arg("-an")
+        .arg("-f")
+        .arg("null")
+        .arg("-y")
+        .arg(null_output());
+
+    let mut second = build_pass(2)?;
+    second.args(&audio_args).arg("-y").arg(output);
+
+    let result = run_ffmpeg_with_progress(
+        first,
+        Some(duration),
+        progress_slice(&mut callback, 0.0, 0.5),
+    )
+    .and_then(|_| {
+        run_ffmpeg_with_progress(
+            second,
+            Some(duration),
+            progress_slice(&mut callback, 0.5, 0.5),
+        )
+    });
+
+    remove_passlogs(&passlog);
+    result?;
+
+    let size = std::fs::metadata(output)
+        .with_context(|| format!("Failed to read {}", output.display()))?
+        .len();
+    callback(ProgressInfo::Log(format!(
+        "Output size: {} (target {}, {:.0}%)",
+        format_size(size),
+        format_size(target),
+        size as f64 / target as f64 * 100.0
+    )));
+    Ok(())
+}
+
+/// Deletes the `<prefix>-0.log` / `.mbtree` (and x265's `<prefix>.log` /
+/// `.cutree`) files a two-pass encode leaves behind. The delimiter matters:
+/// another run's prefix may extend this one (`_12` vs `_123`).
+fn remove_passlogs(prefix: &Path) {
+    let (Some(dir), Some(name)) = (prefix.parent(), prefix.file_name()) else {
+        return;
+    };
+    let name = name.to_string_lossy();
+    let (numbered, x265) = (format!("{}-", name), format!("{}.log", name));
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let file = entry.file_name();
+            let file = file.to_string_lossy();
+            if file.starts_with(&numbered) || file.starts_with(&x265) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MusicOptions {
+    /// Volume of the video's own audio in ffmpeg's syntax: a factor such as
+    /// `0.5` or a gain such as `-6dB` (`1.0` keeps it unchanged).
+    pub reduce_original: String,
+    /// Repeat the track until the video ends.
+    pub loop_music: bool,
+    /// Seconds to skip at the start of the track.
+    pub music_start: f64,
+    /// Seconds into the video before the music comes in.
+    pub delay: f64,
+    pub fade_in: f64,
+    pub fade_out: f64,
+    /// Lower the music whenever the original audio is loud (speech).
+    pub duck: Option<Ducking>,
+}
+
+/// Sidechain compression settings: the original audio drives how far the
+/// music is pulled down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ducking {
+    /// Level (in dB) of the original audio above which ducking starts.
+    pub threshold_db: f64,
+    pub ratio: f64,
+    /// Milliseconds to duck once speech starts.
+    pub attack: f64,
+    /// Milliseconds to recover once speech stops.
+    pub release: f64,
+}
+
+impl Ducking {
+    /// Quick, deep ducking for narration over a music bed.
+    pub const VOICEOVER: Ducking = Ducking {
+        threshold_db: -30.0,
+        ratio: 8.0,
+        attack: 20.0,
+        release: 400.0,
+    };
+
+    /// Gentler ducking that lets music breathe between sentences.
+    pub const VLOG: Ducking = Ducking {
+        threshold_db: -24.0,
+        ratio: 4.0,
+        attack: 50.0,
+        release: 800.0,
+    };
+
+    fn filter(&self) -> String {
+        format!(
+            "sidechaincompress=threshold={}:ratio={}:attack={}:release={}",
+            10f64.powf(self.threshold_db / 20.0),
+            self.ratio,
+            self.attack,
+            self.release
+        )
+    }
+}
+
+impl FromStr for Ducking {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "voiceover" => Ok(Ducking::VOICEOVER),
+            "vlog" => Ok(Ducking::VLOG),
+            _ => Err(anyhow!(
+                "Invalid ducking preset '{}': use voiceover or vlog",
+                s
+            )),
+        }
+    }
+}
+
+impl Default for MusicOptions {
+    fn default() -> Self {
+        Self {
+            reduce_original: "1.0".to_string(),
+            loop_music: false,
+            music_start: 0.0,
+            delay: 0.0,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            duck: None,
+        }
+    }
+}
+
+/// The filter chain that cuts, fades and positions the music track so it
+/// ends with the video (or earlier, if the track runs out without looping).
+fn music_chain(
+    options: &MusicOptions,
+    video_duration: f64,
+    track_duration: f64,
+) -> Result<(String, f64)> {
+    if options.delay >= video_duration {
+        return Err(anyhow!("Music delay is past the end of the video"));
+    }
+    if options.music_start >= track_duration {
+        return Err(anyhow!("Music start offset is past the end of the track"));
+    }
+
+    let room = video_duration - options.delay;
+    let length = if options.loop_music {
+        room
+    } else {
+        room.min(track_duration - options.music_start)
+    };
+
+    let mut chain = format!("atrim=0:{},asetpts=PTS-STARTPTS", length);
+    if options.fade_in > 0.0 {
+        write!(chain, ",afade=t=in:st=0:d={}", options.fade_in.min(length)).unwrap();
+    }
+    if options.fade_out > 0.0 {
+        let fade_out = options.fade_out.min(length);
+        write!(
+            chain,
+            ",afade=t=out:st={}:d={}",
+            length - fade_out,
+            fade_out
+        )
+        .unwrap();
+    }
+    if options.delay > 0.0 {
+        write!(chain, ",adelay={}:all=1", (options.delay * 1000.0).round()).unwrap();
+    }
+    Ok((chain, length))
+}
+
+pub fn add_music<F>(
+    video: &Path,
+    audio: &Path,
+    output: &Path,
+    options: &MusicOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    callback(ProgressInfo::Log("Adding music...".to_string()));
+
+    let video_info = probe::probe(video)?;
+    let has_audio = video_info.has_audio();
+    let video_duration = video_info.duration_secs()?;
+    let track_duration = probe::probe(audio)?.duration_secs()?;
+
+    let (chain, length) = music_chain(options, video_duration, track_duration)?;
+    callback(ProgressInfo::Log(format!(
+        "Music plays from {} for {}{}",
+        probe::format_duration(options.delay),
+        probe::format_duration(length),
+        if options.loop_music { " (looped)" } else { "" }
+    )));
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-i").arg(video);
+    if options.loop_music {
+        command.arg("-stream_loop").arg("-1");
+    }
+    if options.music_start > 0.0 {
+        command.arg("-ss").arg(options.music_start.to_string());
+    }
+    command.arg("-i").arg(audio);
+
+    let filter = match (has_audio, options.duck) {
+        (true, Some(duck)) => {
+            callback(ProgressInfo::Log(format!(
+                "Ducking music under speech (threshold {} dB, ratio {}:1)",
+                duck.threshold_db, duck.ratio
+            )));
+            // The unattenuated original feeds the compressor's sidechain.
+            format!(
+                "[0:a]asplit=2[orig][sc];[orig]volume={}[a0];[1:a]{},volume=1.0[m];\
+                 [m][sc]{}[a1];[a0][a1]amix=inputs=2:duration=first[out]",
+                options.reduce_original,
+                chain,
+                duck.filter()
+            )
+        }
+        (true, None) => format!(
+            "[0:a]volume={}[a0];[1:a]{},volume=1.0[a1];[a0][a1]amix=inputs=2:duration=first[out]",
+            options.reduce_original, chain
+        ),
+        (false, duck) => {
+            if duck.is_some() {
+                callback(ProgressInfo::Log(
+                    "Video has no audio to duck against; mixing music as-is.".to_string(),
+                ));
+            }
+            // Pad with silence so -shortest follows the video, not the music.
+            format!("[1:a]{},apad[out]", chain)
+        }
+    };
+
+    command
+        .arg("-filter_complex")
+        .arg(&filter)
+        .arg("-map")
+        .arg("0:v")
+        .arg("-map")
+        .arg("[out]");
+    if !has_audio {
+        command.arg("-shortest");
+    }
+
+    command.arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, Some(video_duration), callback)
+}
+
+pub fn get_info<F>(input: &Path, json: bool, mut callback: F) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let info = probe::probe(input)?;
+
+    if json {
+        callback(ProgressInfo::Log(serde_json::to_string_pretty(&info)?));
+    } else {
+        for line in info.to_string().lines() {
+            callback(ProgressInfo::Log(line.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// What `timelapse` does with the soundtrack.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimelapseAudio {
+    /// Remove the audio track.
+    #[default]
+    Drop,
+    /// Speed audio up with chained `atempo` stages, keeping the pitch.
+    PreservePitch,
+    /// Resample like a sped-up tape, so the pitch rises with the speed.
+    ShiftPitch,
+}
+
+impl FromStr for TimelapseAudio {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "drop" | "none" => Ok(TimelapseAudio::Drop),
+            "preserve-pitch" | "keep" => Ok(TimelapseAudio::PreservePitch),
+            "shift-pitch" => Ok(TimelapseAudio::ShiftPitch),
+            _ => Err(anyhow!(
+                "Invalid audio mode '{}': use drop, preserve-pitch or shift-pitch",
+                s
+            )),
+        }
+    }
+}
+
+/// How a speed-up drops frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimelapseMethod {
+    /// Retime every frame with `setpts` and let the output rate drop the
+    /// extras. Handles any speed, including slow motion.
+    #[default]
+    Setpts,
+    /// Keep every Nth frame up front with `select`, so the rest are never
+    /// filtered or encoded. Much faster for large speedups.
+    Sample,
+    /// Average each group of N frames with `tmix` for smooth motion blur.
+    Blend,
+}
+
+impl FromStr for TimelapseMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "setpts" | "retime" => Ok(TimelapseMethod::Setpts),
+            "sample" | "select" => Ok(TimelapseMethod::Sample),
+            "blend" | "tmix" => Ok(TimelapseMethod::Blend),
+            _ => Err(anyhow!(
+                "Invalid timelapse method '{}': use setpts, sample or blend",
+                s
+            )),
+        }
+    }
+}
+
+/// Keeps one frame in every `step`, averaging each group into it first when
+/// `blend` is set.
+fn sampling_filter(step: u32, blend: bool) -> String {
+    // tmix averages each frame with the ones before it, so keep the last
+    // frame of every group: that one holds the whole group.
+    let (blend, keep) = if blend {
+        (format!("tmix=frames={},", step), step - 1)
+    } else {
+        (String::new(), 0)
+    };
+    format!(
+        "{}select='eq(mod(n\\,{step})\\,{keep})',setpts=(PTS-STARTPTS)/{step}",
+        blend,
+        step = step,
+        keep = keep
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct TimelapseOptions {
+    /// Playback speed factor; below 1.0 gives slow motion.
+    pub speed: f64,
+    /// Sample and blend round `speed` to a whole number of frames.
+    pub method: TimelapseMethod,
+    pub audio: TimelapseAudio,
+    /// Synthesize in-between frames with motion-compensated interpolation
+    /// (slow motion only).
+    pub interpolate: bool,
+    /// Output frame rate; defaults to the source rate.
+    pub fps: Option<f64>,
+    /// Per-range speeds. When set, `speed` is ignored and any time not
+    /// covered by a segment plays at normal speed.
+    pub ramp: Vec<SpeedSegment>,
+}
+
+/// A time range of the source and the speed it plays back at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedSegment {
+    pub start: f64,
+    /// `None` runs to the end of the input.
+    pub end: Option<f64>,
+    pub speed: f64,
+}
+
+/// Parses a speed ramp such as `10-60@8` or `0:10-1:00@8,1:00-@0.5`: a
+/// comma-separated list of `START-END@SPEED`, where an empty END means the
+/// end of the video.
+pub fn parse_speed_ramp(spec: &str) -> Result<Vec<SpeedSegment>> {
+    let mut segments = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let err = || anyhow!("Invalid ramp segment '{}': use START-END@SPEED", item);
+            let (range, speed) = item.split_once('@').ok_or_else(err)?;
+            let (start, end) = range.split_once('-').ok_or_else(err)?;
+            let speed: f64 = speed.trim().parse().map_err(|_| err())?;
+            if speed <= 0.0 {
+                return Err(anyhow!("Speed in '{}' must be greater than zero", item));
+            }
+            let start = if start.trim().is_empty() {
+                0.0
+            } else {
+                parse_timestamp(start)?
+            };
+            let end = match end.trim() {
+                "" => None,
+                e => Some(parse_timestamp(e)?),
+            };
+            if end.is_some_and(|e| e <= start) {
+                return Err(anyhow!("Segment '{}' ends before it starts", item));
+            }
+            Ok(SpeedSegment { start, end, speed })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for pair in segments.windows(2) {
+        if pair[0].end.is_none_or(|end| end > pair[1].start) {
+            return Err(anyhow!("Speed ramp segments overlap"));
+        }
+    }
+    Ok(segments)
+}
+
+impl Default for TimelapseOptions {
+    fn default() -> Self {
+        Self {
+            speed: 10.0,
+            method: TimelapseMethod::Setpts,
+            audio: TimelapseAudio::Drop,
+            interpolate: false,
+            fps: None,
+            ramp: Vec::new(),
+        }
+    }
+}
+
+/// Builds an `atempo` chain for `speed`. A single atempo stage only accepts
+/// 0.5–2.0, so larger changes are split into several stages.
+fn atempo_chain(speed: f64) -> String {
+    let mut stages = Vec::new();
+    let mut remaining = speed;
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    stages.push(format!("atempo={}", remaining));
+    stages.join(",")
+}
+
+/// The audio filter for changing playback speed by `speed`, or `None` when
+/// the audio should be dropped.
+fn speed_audio_filter(info: &MediaInfo, speed: f64, mode: TimelapseAudio) -> Option<String> {
+    let audio = info.audio()?;
+    match mode {
+        TimelapseAudio::Drop => None,
+        TimelapseAudio::PreservePitch => Some(atempo_chain(speed)),
+        TimelapseAudio::ShiftPitch => {
+            let rate = audio.sample_rate.unwrap_or(48000);
+            Some(format!(
+                "asetrate={},aresample={}",
+                (rate as f64 * speed).round(),
+                rate
+            ))
+        }
+    }
+}
+
+pub fn timelapse<F>(
+    input: &Path,
+    output: &Path,
+    options: &TimelapseOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    callback(ProgressInfo::Log("Creating timelapse...".to_string()));
+
+    if !options.ramp.is_empty() {
+        return speed_ramp(input, output, options, callback);
+    }
+
+    let mut speed = options.speed;
+    if speed <= 0.0 {
+        return Err(anyhow!("Speed factor must be greater than zero"));
+    }
+    if options.interpolate && speed >= 1.0 {
+        return Err(anyhow!(
+            "Frame interpolation only applies to slow motion (speed below 1.0)"
+        ));
+    }
+
+    // (keep every Nth frame, frames to average into each kept one)
+    let sampling = match options.method {
+        TimelapseMethod::Setpts => None,
+        method => {
+            if options.interpolate {
+                return Err(anyhow!(
+                    "Frame interpolation can't be combined with the {:?} method",
+                    method
+                ));
+            }
+            let step = speed.round();
+            if step < 2.0 {
+                return Err(anyhow!(
+                    "The {:?} method needs a speed of at least 2",
+                    method
+                ));
+            }
+            // tmix can't hold more than 1024 frames.
+            if method == TimelapseMethod::Blend && step > 1024.0 {
+                return Err(anyhow!("The blend method supports speeds up to 1024"));
+            }
+            if step != speed {
+                callback(ProgressInfo::Log(format!(
+                    "Rounding speed {} to {}x: frames are kept in whole steps.",
+                    speed, step
+                )));
+            }
+            speed = step;
+            Some((step as u32, method == TimelapseMethod::Blend))
+        }
+    };
+
+    // ffmpeg reports time= against the sped-up output, so scale the expected
+    // length accordingly.
+    let info = probe::probe(input)?;
+    if info.video().is_none() {
+        return Err(anyhow!("{} has no video stream", input.display()));
+    }
+    let expected = info.duration.map(|d| d / speed);
+
+    let mut filter = match sampling {
+        Some((step, blend)) => sampling_filter(step, blend),
+        None => format!("setpts=PTS/{}", speed),
+    };
+    if options.interpolate {
+        // Without an explicit rate, fill the slowed-down clip back up to the
+        // source frame rate with synthesized frames.
+        let fps = options
+            .fps
+            .or_else(|| info.video().and_then(|v| v.fps))
+            .unwrap_or(30.0);
+        callback(ProgressInfo::Log(format!(
+            "Interpolating slow motion to {:.2} fps (this is slow)...",
+            fps
+        )));
+        write!(
+            filter,
+            ",minterpolate=fps={}:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1",
+            fps
+        )
+        .unwrap();
+    } else if let Some(fps) = options.fps {
+        write!(filter, ",fps={}", fps).unwrap();
+    }
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-i").arg(input).arg("-filter:v").arg(&filter);
+
+    match speed_audio_filter(&info, speed, options.audio) {
+        Some(audio_filter) => {
+            command.arg("-filter:a").arg(audio_filter);
+        }
+        None => {
+            if options.audio != TimelapseAudio::Drop {
+                callback(ProgressInfo::Log(
+                    "Input has no audio; output will be silent.".to_string(),
+                ));
+            }
+            command.arg("-an");
+        }
+    }
+
+    command.arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, expected, callback)
+}
+
+/// How the photos of an image-sequence timelapse are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageSort {
+    /// By file name, with numbers compared by value (`img2` before `img10`).
+    #[default]
+    Natural,
+    /// By EXIF capture time; photos without one go last, by name.
+    ExifTime,
+}
+
+impl FromStr for ImageSort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "natural" | "name" => Ok(ImageSort::Natural),
+            "exif" | "exif-time" | "time" => Ok(ImageSort::ExifTime),
+            _ => Err(anyhow!("Invalid sort '{}': use natural or exif", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageSequenceOptions {
+    pub sort: ImageSort,
+    /// Output frame rate: how many photos make one second of video.
+    pub fps: f64,
+    /// Output width; height follows the aspect ratio.
+    pub width: Option<u32>,
+    /// Smooth frame-to-frame exposure jumps.
+    pub deflicker: bool,
+}
+
+impl Default for ImageSequenceOptions {
+    fn default() -> Self {
+        Self {
+            sort: ImageSort::Natural,
+            fps: 30.0,
+            width: None,
+            deflicker: false,
+        }
+    }
+}
+
+/// Builds a timelapse from a folder of still photos, one photo per output
+/// frame.
+pub fn timelapse_from_images<F>(
+    folder: &Path,
+    output: &Path,
+    options: &ImageSequenceOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    if options.fps <= 0.0 {
+        return Err(anyhow!("fps must be greater than zero"));
+    }
+    let mut images = collect_images(&[folder.to_path_buf()])?;
+
+    if options.sort == ImageSort::ExifTime {
+        callback(ProgressInfo::Log(format!(
+            "Reading capture times from {} photos...",
+            images.len()
+        )));
+        let mut timed = images
+            .into_iter()
+            .map(|path| Ok((exif::capture_time(&path)?, path)))
+            .collect::<Result<Vec<_>>>()?;
+        let missing = timed.iter().filter(|(time, _)| time.is_none()).count();
+        if missing > 0 {
+            callback(ProgressInfo::Log(format!(
+                "Warning: {} photos have no EXIF capture time; they go last, by name.",
+                missing
+            )));
+        }
+        // The sort is stable, so photos with equal (or no) times keep their
+        // natural order.
+        timed.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        images = timed.into_iter().map(|(_, path)| path).collect();
+    }
+
+    let frames = images.len() as u64;
+    callback(ProgressInfo::Log(format!(
+        "Creating timelapse from {} photos at {} fps ({})...",
+        frames,
+        options.fps,
+        probe::format_duration(frames as f64 / options.fps)
+    )));
+
+    let mut list = String::new();
+    for image in &images {
+        let path = image
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", image.display()))?;
+        let path = path.to_string_lossy().replace('\'', "'\\''");
+        writeln!(list, "file '{}'\nduration {}", path, 1.0 / options.fps).unwrap();
+    }
+    let list_path = std::env::temp_dir().join(format!("framix_images_{}.txt", std::process::id()));
+    std::fs::write(&list_path, list).context("Failed to write image list")?;
+
+    // Retime by frame index so every photo lasts exactly one output frame,
+    // whatever timestamps the demuxer made up.
+    let mut filter = format!("setpts=N/({}*TB)", options.fps);
+    if options.deflicker {
+        filter.push_str(",deflicker=mode=pm:size=10");
+    }
+    match options.width {
+        Some(width) => write!(filter, ",scale={}:-2", width).unwrap(),
+        None => filter.push_str(",scale=trunc(iw/2)*2:trunc(ih/2)*2"),
+    }
+    filter.push_str(",format=yuv420p");
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-f")
+        .arg("concat")
+        .arg("-safe")
+        .arg("0")
+        .arg("-i")
+        .arg(&list_path)
+        .arg("-vf")
+        .arg(&filter)
+        .arg("-r")
+        .arg(options.fps.to_string())
+        .arg("-an")
+        .arg("-y")
+        .arg(output);
+
+    let result = run_ffmpeg_with_frame_progress(command, frames, callback);
+    let _ = std::fs::remove_file(&list_path);
+    result
+}
+
+/// Renders a timelapse whose speed changes over time. Each range is trimmed
+/// out, retimed (audio and video by the same factor so they stay in sync)
+/// and the pieces are concatenated back together.
+/// Turns sorted ramp segments into `(start, end, speed)` pieces covering the
+/// whole `duration`, filling the gaps between segments with normal speed.
+/// Segments past the end of the input are clipped or dropped.
+fn ramp_pieces(segments: &[SpeedSegment], duration: f64) -> Vec<(f64, f64, f64)> {
+    let mut pieces = Vec::new();
+    let mut cursor = 0.0;
+    for segment in segments {
+        let start = segment.start.min(duration);
+        let end = segment.end.unwrap_or(duration).min(duration);
+        if start > cursor {
+            pieces.push((cursor, start, 1.0));
+        }
+        if end > start {
+            pieces.push((start, end, segment.speed));
+        }
+        cursor = end;
+    }
+    if cursor < duration {
+        pieces.push((cursor, duration, 1.0));
+    }
+    pieces
+}
+
+fn speed_ramp<F>(
+    input: &Path,
+    output: &Path,
+    options: &TimelapseOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    if options.interpolate {
+        return Err(anyhow!(
+            "Frame interpolation is not supported together with a speed ramp"
+        ));
+    }
+    if options.method != TimelapseMethod::Setpts {
+        return Err(anyhow!(
+            "The {:?} method is not supported together with a speed ramp",
+            options.method
+        ));
+    }
+
+    let info = probe::probe(input)?;
+    if info.video().is_none() {
+        return Err(anyhow!("{} has no video stream", input.display()));
+    }
+    let duration = info.duration_secs()?;
+
+    let pieces = ramp_pieces(&options.ramp, duration);
+
+    let audio_filters: Vec<Option<String>> = pieces
+        .iter()
+        .map(|(_, _, speed)| speed_audio_filter(&info, *speed, options.audio))
+        .collect();
+    let has_audio = audio_filters.iter().all(Option::is_some);
+    if !has_audio && options.audio != TimelapseAudio::Drop {
+        callback(ProgressInfo::Log(
+            "Input has no audio; output will be silent.".to_string(),
+        ));
+    }
+
+    let expected: f64 = pieces.iter().map(|(s, e, speed)| (e - s) / speed).sum();
+    callback(ProgressInfo::Log(format!(
+        "Rendering {} speed segments ({} -> {})...",
+        pieces.len(),
+        probe::format_duration(duration),
+        probe::format_duration(expected)
+    )));
+
+    let n = pieces.len();
+    let mut filter = format!("[0:v]split={}", n);
+    for i in 0..n {
+        write!(filter, "[sv{}]", i).unwrap();
+    }
+    if has_audio {
+        write!(filter, ";[0:a]asplit={}", n).unwrap();
+        for i in 0..n {
+            write!(filter, "[sa{}]", i).unwrap();
+        }
+    }
+    for (i, ((start, end, speed), audio_filter)) in pieces.iter().zip(&audio_filters).enumerate() {
+        write!(
+            filter,
+            ";[sv{i}]trim=start={start}:end={end},setpts=(PTS-STARTPTS)/{speed}[v{i}]",
+        )
+        .unwrap();
+        if let (true, Some(audio_filter)) = (has_audio, audio_filter) {
+            write!(
+                filter,
+                ";[sa{i}]atrim=start={start}:end={end},asetpts=PTS-STARTPTS,{audio_filter}[a{i}]",
+            )
+            .unwrap();
+        }
+    }
+    filter.push(';');
+    for i in 0..n {
+        if has_audio {
+            write!(filter, "[v{}][a{}]", i, i).unwrap();
+        } else {
+            write!(filter, "[v{}]", i).unwrap();
+        }
+    }
+    let video_out = if options.fps.is_some() { "cv" } else { "outv" };
+    write!(
+        filter,
+        "concat=n={}:v=1:a={}[{}]",
+        n,
+        if has_audio { 1 } else { 0 },
+        video_out
+    )
+    .unwrap();
+    if has_audio {
+        filter.push_str("[outa]");
+    }
+    if let Some(fps) = options.fps {
+        write!(filter, ";[cv]fps={}[outv]", fps).unwrap();
+    }
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
+        .arg(input)
+        .arg("-filter_complex")
+        .arg(&filter)
+        .arg("-map")
+        .arg("[outv]");
+    if has_audio {
+        command.arg("-map").arg("[outa]");
+    }
+    command.arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, Some(expected), callback)
+}
+
+/// Checks a volume for ffmpeg's `volume` filter: a non-negative factor
+/// (`0.5`) or a gain in decibels (`-6dB`).
+pub fn parse_volume(value: &str) -> Result<String> {
+    let value = value.trim();
+    let err = || {
+        anyhow!(
+            "Invalid volume '{}': use a factor like 0.5 or a gain like -6dB",
+            value
+        )
+    };
+    let lower = value.to_ascii_lowercase();
+    match lower.strip_suffix("db") {
+        Some(gain) => {
+            let gain: f64 = gain.trim().parse().map_err(|_| err())?;
+            if !gain.is_finite() {
+                return Err(err());
+            }
+            Ok(format!("{}dB", gain))
+        }
+        None => match value.parse::<f64>() {
+            Ok(factor) if factor >= 0.0 && factor.is_finite() => Ok(value.to_string()),
+            _ => Err(err()),
+        },
+    }
+}
+
+/// Parses a timestamp given as plain seconds (`90`, `12.5`), `MM:SS` or
+/// `HH:MM:SS(.ms)` into seconds.
+pub fn parse_timestamp(value: &str) -> Result<f64> {
+    let value = value.trim();
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(anyhow!("Invalid timestamp: '{}'", value));
+    }
+
+    let mut secs = 0.0;
+    for part in &parts {
+        let n: f64 = part
+            .parse()
+            .map_err(|_| anyhow!("Invalid timestamp: '{}'", value))?;
+        if !n.is_finite() {
+            return Err(anyhow!("Invalid timestamp: '{}'", value));
+        }
+        if n < 0.0 {
+            return Err(anyhow!("Timestamp cannot be negative: '{}'", value));
+        }
+        secs = secs * 60.0 + n;
+    }
+    Ok(secs)
+}
+
+/// Length of the `start..end` (or `start + duration`) range of a clip that is
+/// `total` seconds long; without either, everything after `start`.
+fn range_length(total: f64, start: f64, end: Option<f64>, duration: Option<f64>) -> Result<f64> {
+    if start >= total {
+        return Err(anyhow!("Start time is past the end of the video"));
+    }
+    let length = match (end, duration) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "Specify either an end time or a duration, not both"
+            ))
+        }
+        (Some(end), None) if end <= start => {
+            return Err(anyhow!("End time must be after the start time"))
+        }
+        (Some(end), None) => end - start,
+        (None, Some(d)) if d <= 0.0 => return Err(anyhow!("Duration must be greater than zero")),
+        (None, Some(d)) => d,
+        (None, None) => total - start,
+    };
+    Ok(length.min(total - start))
+}
+
+/// Cuts `input` down to the range starting at `start` and ending at `end`
+/// (or lasting `duration`). With `accurate` the clip is re-encoded so the cut
+/// lands on the exact frame; otherwise streams are copied and the cut snaps
+/// to the nearest keyframe.
+pub fn trim_video<F>(
+    input: &Path,
+    output: &Path,
+    start: Option<&str>,
+    end: Option<&str>,
+    duration: Option<&str>,
+    accurate: bool,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let start_secs = match start {
+        Some(s) => parse_timestamp(s)?,
+        None => 0.0,
+    };
+
+    let end_secs = end.map(parse_timestamp).transpose()?;
+    let duration_secs = duration.map(parse_timestamp).transpose()?;
+    let total = probe::probe(input)?.duration_secs()?;
+    let expected = range_length(total, start_secs, end_secs, duration_secs)?;
+
+    callback(ProgressInfo::Log(format!(
+        "Trimming {:.2}s from {:.2}s ({})...",
+        expected,
+        start_secs,
+        if accurate { "re-encode" } else { "stream copy" }
+    )));
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-ss")
+        .arg(start_secs.to_string())
+        .arg("-i")
+        .arg(input);
+
+    if end.is_some() || duration.is_some() {
+        command.arg("-t").arg(expected.to_string());
+    }
+
+    if accurate {
+        command.arg("-c:v").arg("libx264").arg("-c:a").arg("aac");
+    } else {
+        command
+            .arg("-c")
+            .arg("copy")
+            .arg("-avoid_negative_ts")
+            .arg("make_zero");
+    }
+
+    command.arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, Some(expected), callback)
+}
+
+/// Loudness targets for `normalize_loudness`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// Integrated loudness in LUFS.
+    pub integrated: f64,
+    /// Maximum true peak in dBTP.
+    pub true_peak: f64,
+    /// Loudness range in LU.
+    pub lra: f64,
+}
+
+impl LoudnessTarget {
+    pub const STREAMING: LoudnessTarget = LoudnessTarget {
+        integrated: -14.0,
+        true_peak: -1.0,
+        lra: 11.0,
+    };
+
+    pub const PODCAST: LoudnessTarget = LoudnessTarget {
+        integrated: -16.0,
+        true_peak: -1.0,
+        lra: 11.0,
+    };
+
+    /// EBU R128.
+    pub const BROADCAST: LoudnessTarget = LoudnessTarget {
+        integrated: -23.0,
+        true_peak: -1.0,
+        lra: 15.0,
+    };
+}
+
+impl FromStr for LoudnessTarget {
+    type Err = anyhow::Error;
+
+    /// A preset name, or an integrated loudness in LUFS such as `-14`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "streaming" => Ok(LoudnessTarget::STREAMING),
+            "podcast" => Ok(LoudnessTarget::PODCAST),
+            "broadcast" | "ebu" => Ok(LoudnessTarget::BROADCAST),
+            other => {
+                let integrated: f64 = other.trim_end_matches("lufs").trim().parse().map_err(|_| {
+                    anyhow!(
+                        "Invalid loudness target '{}': use streaming, podcast, broadcast or a LUFS value",
+                        s
+                    )
+                })?;
+                if !(-70.0..=-5.0).contains(&integrated) {
+                    return Err(anyhow!(
+                        "Integrated loudness must be between -70 and -5 LUFS"
+                    ));
+                }
+                Ok(LoudnessTarget {
+                    integrated,
+                    ..LoudnessTarget::STREAMING
+                })
+            }
+        }
+    }
+}
+
+/// The JSON block `loudnorm` prints to stderr with `print_format=json`.
+/// ffmpeg writes every number as a string.
+#[derive(Debug, serde::Deserialize)]
+struct LoudnormStats {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    output_i: String,
+    output_tp: String,
+    output_lra: String,
+    target_offset: String,
+}
+
+impl LoudnormStats {
+    /// Pulls the stats out of the stderr lines of a loudnorm run.
+    fn from_log(lines: &[String]) -> Result<Self> {
+        let text = lines.join("\n");
+        let start = text
+            .rfind('{')
+            .ok_or_else(|| anyhow!("loudnorm did not report any measurements"))?;
+        let end = text[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("loudnorm measurements were cut off"))?;
+        serde_json::from_str(&text[start..=start + end])
+            .context("Failed to parse loudnorm measurements")
+    }
+}
+
+/// Integrated loudness, true peak and loudness range of one measurement.
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessStats {
+    pub integrated: f64,
+    pub true_peak: f64,
+    pub lra: f64,
+}
+
+impl fmt::Display for LoudnessStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} LUFS, true peak {:.1} dBTP, LRA {:.1} LU",
+            self.integrated, self.true_peak, self.lra
+        )
+    }
+}
+
+fn parse_stat(value: &str) -> f64 {
+    value.trim().parse().unwrap_or(f64::NEG_INFINITY)
+}
+
+/// Whether the output container can carry a video stream; audio-only
+/// outputs such as .wav or .m4a get the audio alone.
+fn holds_video(output: &Path) -> bool {
+    let ext = lowercase_extension(output);
+    matches!(ext.as_str(), "mp4" | "m4v" | "mov" | "mkv" | "webm")
+}
+
+/// Picks an audio encoder that fits the output container.
+fn audio_encoder_for(output: &Path) -> Result<&'static str> {
+    let ext = lowercase_extension(output);
+    match ext.as_str() {
+        "mp4" | "m4v" | "m4a" | "mov" | "mkv" | "aac" => Ok("aac"),
+        "mp3" => Ok("libmp3lame"),
+        "wav" => Ok("pcm_s16le"),
+        "flac" => Ok("flac"),
+        "opus" | "ogg" | "webm" => Ok("libopus"),
+        _ => Err(anyhow!(
+            "Unsupported output format '.{}': use mp4, mov, mkv, webm, m4a, mp3, wav, flac or opus",
+            ext
+        )),
+    }
+}
+
+/// Normalizes loudness with ffmpeg's `loudnorm` (EBU R128) in two passes:
+/// the first measures the input, the second applies a linear gain using
+/// those measurements. Video is stream-copied when the output container
+/// can hold it and dropped otherwise (e.g. a .wav from an .mp4). Returns
+/// the before/after measurements.
+pub fn normalize_loudness<F>(
+    input: &Path,
+    output: &Path,
+    target: &LoudnessTarget,
+    mut callback: F,
+) -> Result<(LoudnessStats, LoudnessStats)>
+where
+    F: FnMut(ProgressInfo),
+{
+    let info = probe::probe(input)?;
+    let audio = info
+        .audio()
+        .ok_or_else(|| anyhow!("{} has no audio to normalize", input.display()))?;
+    let sample_rate = audio.sample_rate.unwrap_or(48000);
+    let duration = info.duration;
+    let encoder = audio_encoder_for(output)?;
+
+    let copy_video = info.video().is_some() && holds_video(output);
+    if copy_video && has_extension(output, "webm") {
+        let codec = info.video().and_then(|v| v.codec.as_deref()).unwrap_or("");
+        if !matches!(codec, "vp8" | "vp9" | "av1") {
+            return Err(anyhow!(
+                "A .webm file can't hold the source's {} video; write .mkv or .mp4 instead",
+                if codec.is_empty() { "unknown" } else { codec }
+            ));
+        }
+    }
+
+    let loudnorm = format!(
+        "loudnorm=I={}:TP={}:LRA={}",
+        target.integrated, target.true_peak, target.lra
+    );
+
+    callback(ProgressInfo::Log(format!(
+        "Measuring loudness (target {} LUFS, {} dBTP, LRA {})...",
+        target.integrated, target.true_peak, target.lra
+    )));
+
+    let mut measure = Command::new("ffmpeg");
+    measure
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg("0:a:0")
+        .arg("-af")
+        .arg(format!("{}:print_format=json", loudnorm))
+        .arg("-f")
+        .arg("null")
+        .arg(null_output());
+
+    let mut lines = Vec::new();
+    {
+        let mut forward = progress_slice(&mut callback, 0.0, 0.5);
+        run_ffmpeg_with_progress(measure, duration, |info| {
+            if let ProgressInfo::Log(line) = &info {
+                lines.push(line.clone());
+            }
+            forward(info);
+        })?;
+    }
+    let measured = LoudnormStats::from_log(&lines)?;
+
+    let before = LoudnessStats {
+        integrated: parse_stat(&measured.input_i),
+        true_peak: parse_stat(&measured.input_tp),
+        lra: parse_stat(&measured.input_lra),
+    };
+    callback(ProgressInfo::Log(format!("Measured: {}", before)));
+
+    // Silent input has no measurable loudness; loudnorm would fail on -inf.
+    if !before.integrated.is_finite() {
+        return Err(anyhow!(
+            "{} is silent; nothing to normalize",
+            input.display()
+        ));
+    }
+
+    let apply = format!(
+        "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=json",
+        loudnorm,
+        measured.input_i,
+        measured.input_tp,
+        measured.input_lra,
+        measured.input_thresh,
+        measured.target_offset
+    );
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-hide_banner").arg("-i").arg(input);
+    if copy_video {
+        command.arg("-map").arg("0:v:0").arg("-c:v").arg("copy");
+    } else {
+        command.arg("-vn");
+    }
+    // loudnorm resamples to 192 kHz internally, so restore the source rate.
+    // Opus only runs at 48 kHz (and a few lower rates), so it always gets 48k.
+    let sample_rate = if encoder == "libopus" {
+        48000
+    } else {
+        sample_rate
+    };
+    command
+        .arg("-map")
+        .arg("0:a:0")
+        .arg("-af")
+        .arg(apply)
+        .arg("-ar")
+        .arg(sample_rate.to_string())
+        .arg("-c:a")
+        .arg(encoder)
+        .arg("-y")
+        .arg(output);
+
+    let mut lines = Vec::new();
+    {
+        let mut forward = progress_slice(&mut callback, 0.5, 0.5);
+        run_ffmpeg_with_progress(command, duration, |info| {
+            if let ProgressInfo::Log(line) = &info {
+                lines.push(line.clone());
+            }
+            forward(info);
+        })?;
+    }
+    let applied = LoudnormStats::from_log(&lines)?;
+
+    let after = LoudnessStats {
+        integrated: parse_stat(&applied.output_i),
+        true_peak: parse_stat(&applied.output_tp),
+        lra: parse_stat(&applied.output_lra),
+    };
+    Ok((before, after))
+}
+
+/// Audio-only output formats for `extract_audio`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    Wav,
+    Aac,
+    Opus,
+    Flac,
+}
+
+impl FromStr for AudioFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mp3" => Ok(AudioFormat::Mp3),
+            "wav" => Ok(AudioFormat::Wav),
+            "aac" | "m4a" => Ok(AudioFormat::Aac),
+            "opus" | "ogg" => Ok(AudioFormat::Opus),
+            "flac" => Ok(AudioFormat::Flac),
+            _ => Err(anyhow!(
+                "Invalid audio format '{}': use mp3, wav, aac, opus or flac",
+                s
+            )),
+        }
+    }
+}
+
+impl AudioFormat {
+    fn from_path(path: &Path) -> Result<Self> {
+        lowercase_extension(path).parse().map_err(|_| {
+            anyhow!(
+                "Can't tell the audio format from '{}'; pass --format or use .mp3, .wav, .m4a, .aac, .opus or .flac",
+                path.display()
+            )
+        })
+    }
+
+    fn encoder(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::Wav => "pcm_s16le",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Opus => "libopus",
+            AudioFormat::Flac => "flac",
+        }
+    }
+
+    fn is_lossless(self) -> bool {
+        matches!(self, AudioFormat::Wav | AudioFormat::Flac)
+    }
+
+    /// Whether a source stream in `codec` can be copied into this format.
+    fn can_copy(self, codec: &str) -> bool {
+        match self {
+            AudioFormat::Mp3 => codec == "mp3",
+            // WAV only holds little-endian interleaved PCM (and the
+            // companded telephone codecs); big-endian or planar PCM from
+            // .mov/.aiff sources has to be re-encoded.
+            AudioFormat::Wav => matches!(
+                codec,
+                "pcm_u8"
+                    | "pcm_s16le"
+                    | "pcm_s24le"
+                    | "pcm_s32le"
+                    | "pcm_f32le"
+                    | "pcm_f64le"
+                    | "pcm_alaw"
+                    | "pcm_mulaw"
+            ),
+            AudioFormat::Aac => codec == "aac",
+            AudioFormat::Opus => codec == "opus",
+            AudioFormat::Flac => codec == "flac",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Index among the input's audio streams (0 = first).
+    pub stream: usize,
+    /// Output format; taken from the output extension when unset.
+    pub format: Option<AudioFormat>,
+    /// Bitrate in kb/s for lossy formats. Forces a re-encode.
+    pub bitrate: Option<u32>,
+}
+
+/// Looks up the `index`-th stream of `kind`, listing what's there when it's
+/// out of range.
+fn nth_stream(
+    info: &MediaInfo,
+    kind: probe::StreamKind,
+    index: usize,
+) -> Result<&probe::StreamInfo> {
+    let name = format!("{:?}", kind).to_ascii_lowercase();
+    let streams: Vec<_> = info.streams.iter().filter(|s| s.kind == kind).collect();
+    if streams.is_empty() {
+        return Err(anyhow!("{} has no {} streams", info.path, name));
+    }
+    streams.get(index).copied().ok_or_else(|| {
+        let available = streams
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let mut label = format!("{}: {}", i, s.codec.as_deref().unwrap_or("unknown"));
+                if let Some(lang) = s.tags.get("language") {
+                    write!(label, " ({})", lang).unwrap();
+                }
+                label
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        anyhow!(
+            "{} has no {} stream {}; available: {}",
+            info.path,
+            name,
+            index,
+            available
+        )
+    })
+}
+
+/// Writes one audio stream of `input` to an audio-only file, copying it
+/// when the codec already matches the output format.
+pub fn extract_audio<F>(
+    input: &Path,
+    output: &Path,
+    options: &ExtractOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let info = probe::probe(input)?;
+    let stream = nth_stream(&info, probe::StreamKind::Audio, options.stream)?;
+    let format = match options.format {
+        Some(format) => format,
+        None => AudioFormat::from_path(output)?,
+    };
+    let codec = stream.codec.as_deref().unwrap_or_default();
+    let copy = format.can_copy(codec) && options.bitrate.is_none();
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg(format!("0:a:{}", options.stream))
+        .arg("-vn");
+
+    if copy {
+        callback(ProgressInfo::Log(format!(
+            "Copying {} audio stream {}...",
+            codec, options.stream
+        )));
+        command.arg("-c:a").arg("copy");
+    } else {
+        callback(ProgressInfo::Log(format!(
+            "Encoding audio stream {} ({}) to {:?}...",
+            options.stream, codec, format
+        )));
+        command.arg("-c:a").arg(format.encoder());
+        if !format.is_lossless() {
+            let bitrate = options.bitrate.unwrap_or(192);
+            command.arg("-b:a").arg(format!("{}k", bitrate));
+        }
+    }
+
+    // Explicit muxers so `--format` wins over an unusual extension.
+    let muxer = match format {
+        AudioFormat::Mp3 => "mp3",
+        AudioFormat::Wav => "wav",
+        AudioFormat::Aac if has_extension(output, "aac") => "adts",
+        AudioFormat::Aac => "ipod",
+        AudioFormat::Opus => "ogg",
+        AudioFormat::Flac => "flac",
+    };
+    command.arg("-f").arg(muxer).arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, info.duration, callback)
+}
+
+/// Copies `input` without any of its audio streams.
+pub fn strip_audio<F>(input: &Path, output: &Path, mut callback: F) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let info = probe::probe(input)?;
+    if info.video().is_none() {
+        return Err(anyhow!("{} has no video to keep", input.display()));
+    }
+    callback(ProgressInfo::Log("Removing audio...".to_string()));
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg("0")
+        .arg("-map")
+        .arg("-0:a")
+        .arg("-c")
+        .arg("copy")
+        .arg("-y")
+        .arg(output);
+
+    run_ffmpeg_with_progress(command, info.duration, callback)
+}
+
+/// Whether a container at `output` can hold `codec` audio as-is.
+fn container_accepts_audio(output: &Path, codec: &str) -> bool {
+    let ext = lowercase_extension(output);
+    match ext.as_str() {
+        "mp4" | "m4v" | "mov" => matches!(codec, "aac" | "mp3" | "alac" | "ac3" | "eac3"),
+        "mkv" => true,
+        "webm" => matches!(codec, "opus" | "vorbis"),
+        _ => false,
+    }
+}
+
+/// Swaps the audio of `video` for one stream of `audio`, without mixing.
+/// The video is copied and the output keeps the video's length.
+pub fn replace_audio<F>(
+    video: &Path,
+    audio: &Path,
+    output: &Path,
+    stream: usize,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let video_info = probe::probe(video)?;
+    if video_info.video().is_none() {
+        return Err(anyhow!("{} has no video stream", video.display()));
+    }
+    let video_duration = video_info.duration_secs()?;
+    let audio_info = probe::probe(audio)?;
+    let codec = nth_stream(&audio_info, probe::StreamKind::Audio, stream)?
+        .codec
+        .clone()
+        .unwrap_or_default();
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
+        .arg(video)
+        .arg("-i")
+        .arg(audio)
+        .arg("-map")
+        .arg("0:v")
+        .arg("-map")
+        .arg(format!("1:a:{}", stream))
+        .arg("-c:v")
+        .arg("copy");
+
+    if container_accepts_audio(output, &codec) {
+        callback(ProgressInfo::Log(format!(
+            "Replacing audio (copying {})...",
+            codec
+        )));
+        command.arg("-c:a").arg("copy");
+    } else {
+        let encoder = audio_encoder_for(output)?;
+        callback(ProgressInfo::Log(format!(
+            "Replacing audio (re-encoding {} with {})...",
+            codec, encoder
+        )));
+        command.arg("-c:a").arg(encoder).arg("-b:a").arg("192k");
+    }
+
+    command
+        .arg("-t")
+        .arg(video_duration.to_string())
+        .arg("-y")
+        .arg(output);
+
+    run_ffmpeg_with_progress(command, Some(video_duration), callback)
+}
+
+/// Dithering used by `paletteuse` when mapping frames onto the GIF palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Ordered dithering; the scale (0-5) trades pattern visibility for banding.
+    Bayer(u8),
+    FloydSteinberg,
+    Sierra2,
+    #[default]
+    Sierra2_4a,
+    None,
+}
+
+impl FromStr for Dither {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        if let Some(scale) = s.strip_prefix("bayer") {
+            let scale = scale.trim_start_matches(':');
+            let scale = if scale.is_empty() {
+                2
+            } else {
+                scale
+                    .parse()
+                    .ok()
+                    .filter(|s| *s <= 5)
+                    .ok_or_else(|| anyhow!("Bayer scale must be 0-5, got '{}'", scale))?
+            };
+            return Ok(Dither::Bayer(scale));
+        }
+        match s.as_str() {
+            "floyd_steinberg" | "floyd" => Ok(Dither::FloydSteinberg),
+            "sierra2" => Ok(Dither::Sierra2),
+            "sierra2_4a" | "sierra" => Ok(Dither::Sierra2_4a),
+            "none" => Ok(Dither::None),
+            _ => Err(anyhow!(
+                "Invalid dither '{}': use bayer[:0-5], floyd_steinberg, sierra2, sierra2_4a or none",
+                s
+            )),
+        }
+    }
+}
+
+impl Dither {
+    fn paletteuse_args(self) -> String {
+        match self {
+            Dither::Bayer(scale) => format!("dither=bayer:bayer_scale={}", scale),
+            Dither::FloydSteinberg => "dither=floyd_steinberg".to_string(),
+            Dither::Sierra2 => "dither=sierra2".to_string(),
+            Dither::Sierra2_4a => "dither=sierra2_4a".to_string(),
+            Dither::None => "dither=none".to_string(),
+        }
+    }
+}
+
+/// Converts a play count (0 = forever) into the gif muxer's `-loop`, where
+/// -1 plays once and N repeats N more times.
+fn gif_repeats(loop_count: u32) -> i64 {
+    match loop_count {
+        0 => 0,
+        1 => -1,
+        n => n as i64 - 1,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GifOptions {
+    pub start: f64,
+    pub end: Option<f64>,
+    pub duration: Option<f64>,
+    pub fps: f64,
+    /// Output width; height follows the aspect ratio.
+    pub width: Option<u32>,
+    /// How many times the animation plays; 0 loops forever.
+    pub loop_count: u32,
+    pub dither: Dither,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            end: None,
+            duration: None,
+            fps: 12.0,
+            width: Some(480),
+            loop_count: 0,
+            dither: Dither::default(),
+        }
+    }
+}
+
+/// Renders a clip as an animated GIF, WebP or APNG, picked by the output
+/// extension. GIFs go through palettegen/paletteuse so each clip gets its
+/// own 256-colour palette.
+pub fn make_gif<F>(input: &Path, output: &Path, options: &GifOptions, mut callback: F) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    if options.fps <= 0.0 {
+        return Err(anyhow!("fps must be greater than zero"));
+    }
+    let ext = lowercase_extension(output);
+    if !matches!(ext.as_str(), "gif" | "webp" | "apng" | "png") {
+        return Err(anyhow!(
+            "Unsupported animation format '.{}': use .gif, .webp or .apng",
+            ext
+        ));
+    }
+
+    let total = probe::probe(input)?.duration_secs()?;
+    let length = range_length(total, options.start, options.end, options.duration)?;
+
+    let mut filter = format!("fps={}", options.fps);
+    if let Some(width) = options.width {
+        write!(filter, ",scale={}:-2:flags=lanczos", width).unwrap();
+    }
+
+    let clip = |command: &mut Command| {
+        command
+            .arg("-ss")
+            .arg(options.start.to_string())
+            .arg("-t")
+            .arg(length.to_string())
+            .arg("-i")
+            .arg(input);
+    };
+
+    callback(ProgressInfo::Log(format!(
+        "Rendering {:.2}s from {} as .{} at {} fps...",
+        length,
+        probe::format_duration(options.start),
+        ext,
+        options.fps
+    )));
+
+    if ext == "gif" {
+        let palette =
+            std::env::temp_dir().join(format!("framix_palette_{}.png", std::process::id()));
+
+        let mut command = Command::new("ffmpeg");
+        clip(&mut command);
+        command
+            .arg("-vf")
+            .arg(format!("{},palettegen=stats_mode=diff", filter))
+            .arg("-y")
+            .arg(&palette);
+        let result = run_ffmpeg_with_progress(
+            command,
+            Some(length),
+            progress_slice(&mut callback, 0.0, 0.5),
+        )
+        .and_then(|_| {
+            let mut command = Command::new("ffmpeg");
+            clip(&mut command);
+            command
+                .arg("-i")
+                .arg(&palette)
+                .arg("-lavfi")
+                .arg(format!(
+                    "{}[x];[x][1:v]paletteuse={}:diff_mode=rectangle",
+                    filter,
+                    options.dither.paletteuse_args()
+                ))
+                .arg("-loop")
+                .arg(gif_repeats(options.loop_count).to_string())
+                .arg("-y")
+                .arg(output);
+            run_ffmpeg_with_progress(
+                command,
+                Some(length),
+                progress_slice(&mut callback, 0.5, 0.5),
+            )
+        });
+        let _ = std::fs::remove_file(&palette);
+        result?;
+    } else {
+        let mut command = Command::new("ffmpeg");
+        clip(&mut command);
+        command.arg("-vf").arg(&filter).arg("-an");
+        if ext == "webp" {
+            command
+                .arg("-c:v")
+                .arg("libwebp")
+                .arg("-quality")
+                .arg("80")
+                .arg("-loop")
+                .arg(options.loop_count.to_string());
+        } else {
+            command
+                .arg("-c:v")
+                .arg("apng")
+                .arg("-plays")
+                .arg(options.loop_count.to_string())
+                .arg("-f")
+                .arg("apng");
+        }
+        command.arg("-y").arg(output);
+        run_ffmpeg_with_progress(command, Some(length), &mut callback)?;
+    }
+
+    let size = std::fs::metadata(output)
+        .with_context(|| format!("Failed to read {}", output.display()))?
+        .len();
+    callback(ProgressInfo::Log(format!(
+        "Output size: {} ({})",
+        format_size(size),
+        output.display()
+    )));
+    Ok(())
+}
+
+/// What `thumbnails` grabs from the input.
+pub enum ThumbnailMode {
+    /// One frame at each timestamp (in seconds).
+    At(Vec<f64>),
+    /// N representative frames, one per even slice of the video, picked by
+    /// ffmpeg's `thumbnail` filter.
+    Representative(u32),
+    /// A single `columns`x`rows` grid of frames at even intervals.
+    Sheet {
+        columns: u32,
+        rows: u32,
+        labels: bool,
+    },
+}
+
+/// Parses a contact sheet grid such as `4x3` into (columns, rows).
+pub fn parse_grid(value: &str) -> Result<(u32, u32)> {
+    let (columns, rows) = value
+        .trim()
+        .to_ascii_lowercase()
+        .split_once('x')
+        .and_then(|(c, r)| Some((c.parse::<u32>().ok()?, r.parse::<u32>().ok()?)))
+        .ok_or_else(|| anyhow!("Invalid grid '{}': expected COLUMNSxROWS, e.g. 4x3", value))?;
+    if columns == 0 || rows == 0 {
+        return Err(anyhow!("Grid must have at least one column and one row"));
+    }
+    Ok((columns, rows))
+}
+
+/// Replaces the printf-style counter in `template` (`%d`, `%03d`) with `n`.
+fn expand_counter(template: &str, n: usize) -> String {
+    counter_regex()
+        .replace(template, |caps: &regex::Captures| {
+            let width: usize = caps[2].parse().unwrap_or(0);
+            if &caps[1] == "0" {
+                format!("{:0width$}", n, width = width)
+            } else {
+                format!("{:width$}", n, width = width)
+            }
+        })
+        .into_owned()
+}
+
+/// Writes a single `columns`x`rows` grid of frames taken at even intervals,
+/// optionally stamped with their timestamps.
+fn contact_sheet<F>(
+    input: &Path,
+    output: &str,
+    total: f64,
+    scale: &str,
+    (columns, rows): (u32, u32),
+    labels: bool,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let count = columns * rows;
+    let interval = total / count as f64;
+    // Sample from the middle of each slice rather than its first frame,
+    // which is often black.
+    let offset = interval / 2.0;
+    callback(ProgressInfo::Log(format!(
+        "Building a {}x{} contact sheet, one frame every {:.2}s...",
+        columns, rows, interval
+    )));
+
+    let mut filter = format!("fps=1/{},{}", interval, scale);
+    if labels {
+        write!(
+            filter,
+            ",drawtext=text='%{{pts\\:hms\\:{}}}':x=6:y=h-th-6:fontsize=h/12:\
+             fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4",
+            offset
+        )
+        .unwrap();
+    }
+    write!(
+        filter,
+        ",tile={}x{}:padding=4:margin=4:color=black",
+        columns, rows
+    )
+    .unwrap();
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-ss")
+        .arg(offset.to_string())
+        .arg("-i")
+        .arg(input)
+        .arg("-vf")
+        .arg(filter)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-q:v")
+        .arg("2")
+        .arg("-update")
+        .arg("1")
+        .arg("-y")
+        .arg(output);
+    run_ffmpeg_with_progress(command, Some(total - offset), callback)
+}
+
+/// Grabs still frames from `input`. For several frames `output` must contain
+/// a counter such as `thumb_%02d.jpg` (numbering starts at 1); a contact
+/// sheet or a single frame is written to `output` as-is. `width` scales each
+/// frame, keeping the aspect ratio.
+pub fn thumbnails<F>(
+    input: &Path,
+    output: &str,
+    mode: &ThumbnailMode,
+    width: Option<u32>,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let info = probe::probe(input)?;
+    let video = info
+        .video()
+        .ok_or_else(|| anyhow!("{} has no video stream", input.display()))?;
+    let total = info.duration_secs()?;
+    let scale = width
+        .map(|w| format!("scale={}:-2", w))
+        .unwrap_or_else(|| "null".to_string());
+
+    // (seek position, length to read) for each output image
+    let grabs: Vec<(f64, Option<f64>)> = match mode {
+        ThumbnailMode::At(times) => {
+            if let Some(t) = times.iter().find(|t| **t >= total) {
+                return Err(anyhow!(
+                    "{} is past the end of the video ({})",
+                    probe::format_duration(*t),
+                    probe::format_duration(total)
+                ));
+            }
+            times.iter().map(|t| (*t, None)).collect()
+        }
+        ThumbnailMode::Representative(count) => {
+            if *count == 0 {
+                return Err(anyhow!("Number of thumbnails must be greater than zero"));
+            }
+            let slice = total / *count as f64;
+            (0..*count)
+                .map(|i| (i as f64 * slice, Some(slice)))
+                .collect()
+        }
+        ThumbnailMode::Sheet {
+            columns,
+            rows,
+            labels,
+        } => {
+            return contact_sheet(
+                input,
+                output,
+                total,
+                &scale,
+                (*columns, *rows),
+                *labels,
+                callback,
+            )
+        }
+    };
+
+    if grabs.len() > 1 {
+        validate_counter_pattern(output, "thumb_%02d.jpg")?;
+    }
+
+    let fps = video.fps.unwrap_or(25.0);
+
+    let count = grabs.len();
+    for (i, (start, length)) in grabs.into_iter().enumerate() {
+        let path = expand_counter(output, i + 1);
+        callback(ProgressInfo::Log(format!(
+            "Thumbnail {}/{} at {} -> {}",
+            i + 1,
+            count,
+            probe::format_duration(start),
+            path
+        )));
+
+        let mut command = Command::new("ffmpeg");
+        command.arg("-ss").arg(start.to_string());
+        let filter = match length {
+            Some(length) => {
+                command.arg("-t").arg(length.to_string());
+                // The thumbnail filter buffers a whole batch, so cap it to
+                // keep memory in check on long slices.
+                let batch = ((length * fps) as u32).clamp(1, 300);
+                format!("{},thumbnail={}", scale, batch)
+            }
+            None => scale.clone(),
+        };
+        command
+            .arg("-i")
+            .arg(input)
+            .arg("-vf")
+            .arg(filter)
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-q:v")
+            .arg("2")
+            .arg("-update")
+            .arg("1")
+            .arg("-y")
+            .arg(&path);
+
+        run_ffmpeg_with_progress(
+            command,
+            length,
+            progress_slice(&mut callback, i as f64 / count as f64, 1.0 / count as f64),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Which frames `extract_frames` writes out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameSelection {
+    All,
+    /// Every Nth decoded frame, starting with the first.
+    EveryNth(u32),
+    /// Resample to this many frames per second.
+    Fps(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct FramesOptions {
+    pub start: f64,
+    pub end: Option<f64>,
+    pub duration: Option<f64>,
+    pub selection: FrameSelection,
+    /// JPEG quality from 1 (smallest) to 100 (best); ignored for PNG.
+    pub jpeg_quality: u8,
+}
+
+impl Default for FramesOptions {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            end: None,
+            duration: None,
+            selection: FrameSelection::All,
+            jpeg_quality: 90,
+        }
+    }
+}
+
+/// Writes frames from a range of `input` to a numbered image sequence.
+/// `output_template` needs a counter such as `frame_%05d.png`; numbering
+/// starts at 1 and the extension picks PNG or JPEG.
+pub fn extract_frames<F>(
+    input: &Path,
+    output_template: &str,
+    options: &FramesOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    validate_counter_pattern(output_template, "frame_%05d.png")?;
+    let jpeg = match lowercase_extension(Path::new(output_template)).as_str() {
+        "png" => false,
+        "jpg" | "jpeg" => true,
+        _ => return Err(anyhow!("Frames can be written as .png, .jpg or .jpeg")),
+    };
+    if !(1..=100).contains(&options.jpeg_quality) {
+        return Err(anyhow!("JPEG quality must be between 1 and 100"));
+    }
+
+    let info = probe::probe(input)?;
+    let fps = info
+        .video()
+        .ok_or_else(|| anyhow!("{} has no video stream", input.display()))?
+        .fps
+        .unwrap_or(25.0);
+    let length = range_length(
+        info.duration_secs()?,
+        options.start,
+        options.end,
+        options.duration,
+    )?;
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-ss")
+        .arg(options.start.to_string())
+        .arg("-t")
+        .arg(length.to_string())
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg("0:v:0");
+
+    // `-vsync` rather than `-fps_mode`, which needs FFmpeg 5.1; newer
+    // releases still accept `-vsync` as an alias.
+    let estimate = match options.selection {
+        FrameSelection::All => {
+            command.arg("-vsync").arg("passthrough");
+            length * fps
+        }
+        FrameSelection::EveryNth(n) => {
+            if n == 0 {
+                return Err(anyhow!("Frame step must be at least 1"));
+            }
+            command
+                .arg("-vf")
+                .arg(format!("select='not(mod(n\\,{}))'", n))
+                .arg("-vsync")
+                .arg("vfr");
+            length * fps / n as f64
+        }
+        FrameSelection::Fps(rate) => {
+            if rate <= 0.0 {
+                return Err(anyhow!("fps must be greater than zero"));
+            }
+            command.arg("-vf").arg(format!("fps={}", rate));
+            length * rate
+        }
+    };
+
+    if jpeg {
+        command
+            .arg("-q:v")
+            .arg(jpeg_qscale(options.jpeg_quality).to_string());
+    }
+
+    callback(ProgressInfo::Log(format!(
+        "Exporting about {} frames from {} ({:.2}s)...",
+        estimate.ceil() as u64,
+        probe::format_duration(options.start),
+        length
+    )));
+
+    command
+        .arg("-start_number")
+        .arg("1")
+        .arg("-y")
+        .arg(output_template);
+
+    run_ffmpeg_with_progress(command, Some(length), callback)
+}
+
+/// Maps a 1-100 JPEG quality onto mjpeg's qscale, which runs from 2 (best)
+/// to 31 (worst).
+fn jpeg_qscale(quality: u8) -> u32 {
+    31 - (quality as u32 - 1) * 29 / 99
+}
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "bmp", "tif", "tiff"];
+
+/// Orders strings the way people expect numbered files to sort, so
+/// `img2.jpg` comes before `img10.jpg`.
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    // Sorting calls this O(n log n) times, so build the regex once.
+    static RUNS: OnceLock<Regex> = OnceLock::new();
+    let digits = RUNS.get_or_init(|| Regex::new(r"\d+|\D+").unwrap());
+    let mut left = digits.find_iter(a).map(|m| m.as_str());
+    let mut right = digits.find_iter(b).map(|m| m.as_str());
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(l), Some(r)) => {
+                let numeric = l.as_bytes()[0].is_ascii_digit() && r.as_bytes()[0].is_ascii_digit();
+                if numeric {
+                    let (l_trim, r_trim) = (l.trim_start_matches('0'), r.trim_start_matches('0'));
+                    l_trim
+                        .len()
+                        .cmp(&r_trim.len())
+                        .then_with(|| l_trim.cmp(r_trim))
+                } else {
+                    l.to_lowercase().cmp(&r.to_lowercase())
+                }
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Expands directories in `inputs` into the images they contain, in natural
+/// order. Files are kept in the order given.
+pub fn collect_images(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    for input in inputs {
+        if !input.is_dir() {
+            images.push(input.clone());
+            continue;
+        }
+        let mut found: Vec<PathBuf> = std::fs::read_dir(input)
+            .with_context(|| format!("Failed to read {}", input.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| IMAGE_EXTENSIONS.contains(&lowercase_extension(path).as_str()))
+            .collect();
+        found.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+        if found.is_empty() {
+            return Err(anyhow!("No images found in {}", input.display()));
+        }
+        images.extend(found);
+    }
+    if images.is_empty() {
+        return Err(anyhow!("No images provided"));
+    }
+    Ok(images)
+}
+
+/// How an image that doesn't match the canvas aspect ratio is fitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlideFit {
+    /// Show the whole image, letterboxed with black bars.
+    #[default]
+    Contain,
+    /// Fill the canvas, cropping the overflow.
+    Cover,
+}
+
+impl FromStr for SlideFit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "contain" | "pad" => Ok(SlideFit::Contain),
+            "cover" | "crop" => Ok(SlideFit::Cover),
+            _ => Err(anyhow!("Invalid fit '{}': use contain or cover", s)),
+        }
+    }
+}
+
+impl SlideFit {
+    fn filter(self, width: u32, height: u32) -> String {
+        match self {
+            SlideFit::Contain => format!(
+                "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+                w = width,
+                h = height
+            ),
+            SlideFit::Cover => format!(
+                "scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h}",
+                w = width,
+                h = height
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlideshowOptions {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    /// Seconds per slide: one value for every slide or one per slide.
+    pub durations: Vec<f64>,
+    /// Same layout as `CombineOptions::transitions`.
+    pub transitions: Vec<Option<Transition>>,
+    pub fit: SlideFit,
+    /// Slow zoom (alternating in and out) on every slide.
+    pub ken_burns: bool,
+    /// Soundtrack, cut and faded with `music_chain`.
+    pub music: Option<PathBuf>,
+    pub music_options: MusicOptions,
+}
+
+impl Default for SlideshowOptions {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            fps: 30.0,
+            durations: vec![4.0],
+            transitions: Vec::new(),
+            fit: SlideFit::Contain,
+            ken_burns: false,
+            music: None,
+            music_options: MusicOptions::default(),
+        }
+    }
+}
+
+/// How far the Ken Burns effect zooms over one slide.
+const KEN_BURNS_ZOOM: f64 = 0.15;
+
+/// Builds a video from still images, fitted to one canvas and joined with
+/// the same transition chain `combine_videos` uses.
+pub fn slideshow<F>(
+    images: &[PathBuf],
+    output: &Path,
+    options: &SlideshowOptions,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    if images.is_empty() {
+        return Err(anyhow!("No images provided"));
+    }
+    if options.fps <= 0.0 {
+        return Err(anyhow!("fps must be greater than zero"));
+    }
+    // yuv420p needs even dimensions.
+    let width = options.width + options.width % 2;
+    let height = options.height + options.height % 2;
+
+    let durations: Vec<f64> = match options.durations.len() {
+        1 => vec![options.durations[0]; images.len()],
+        n if n == images.len() => options.durations.clone(),
+        n => {
+            return Err(anyhow!(
+                "Got {} durations for {} images; give one for all slides or one per slide",
+                n,
+                images.len()
+            ))
+        }
+    };
+    if durations.iter().any(|d| *d <= 0.0) {
+        return Err(anyhow!("Slide durations must be greater than zero"));
+    }
+
+    let joins = images.len() - 1;
+    let transitions = transitions_per_join(&options.transitions, joins)?;
+
+    callback(ProgressInfo::Log(format!(
+        "Building a slideshow of {} images at {}x{} @ {} fps...",
+        images.len(),
+        width,
+        height,
+        options.fps
+    )));
+
+    let mut command = Command::new("ffmpeg");
+    let mut filter = String::new();
+    for (i, (image, duration)) in images.iter().zip(&durations).enumerate() {
+        if options.ken_burns {
+            // zoompan turns one still into `frames` frames; render at twice
+            // the canvas size first so the slow zoom doesn't visibly step.
+            let frames = (duration * options.fps).ceil() as u64;
+            let zoom = if i % 2 == 0 {
+                format!("1+{}*on/{}", KEN_BURNS_ZOOM, frames)
+            } else {
+                format!("{}-{}*on/{}", 1.0 + KEN_BURNS_ZOOM, KEN_BURNS_ZOOM, frames)
+            };
+            command.arg("-i").arg(image);
+            write!(
+                filter,
+                "[{i}:v]{fit},zoompan=z='{zoom}':x='(iw-iw/zoom)/2':y='(ih-ih/zoom)/2':\
+                 d={frames}:s={w}x{h}:fps={fps}",
+                i = i,
+                fit = options.fit.filter(width * 2, height * 2),
+                zoom = zoom,
+                frames = frames,
+                w = width,
+                h = height,
+                fps = options.fps
+            )
+            .unwrap();
+        } else {
+            command
+                .arg("-loop")
+                .arg("1")
+                .arg("-framerate")
+                .arg(options.fps.to_string())
+                .arg("-t")
+                .arg(duration.to_string())
+                .arg("-i")
+                .arg(image);
+            write!(filter, "[{}:v]{}", i, options.fit.filter(width, height)).unwrap();
+        }
+        write!(
+            filter,
+            ",setsar=1,fps={},format=yuv420p,trim=duration={},setpts=PTS-STARTPTS[v{}];",
+            options.fps, duration, i
+        )
+        .unwrap();
+    }
+
+    let names: Vec<String> = images.iter().map(|p| p.display().to_string()).collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let length = if joins == 0 {
+        filter.push_str("[v0]null[outv]");
+        durations[0]
+    } else {
+        write_transition_chain(&mut filter, &names, &durations, &transitions, false)?
+    };
+
+    if let Some(music) = &options.music {
+        let track_duration = probe::probe(music)?.duration_secs()?;
+        let (chain, _) = music_chain(&options.music_options, length, track_duration)?;
+        if options.music_options.loop_music {
+            command.arg("-stream_loop").arg("-1");
+        }
+        if options.music_options.music_start > 0.0 {
+            command
+                .arg("-ss")
+                .arg(options.music_options.music_start.to_string());
+        }
+        command.arg("-i").arg(music);
+        write!(filter, ";[{}:a]{},apad[outa]", images.len(), chain).unwrap();
+    }
+
+    command
+        .arg("-filter_complex")
+        .arg(&filter)
+        .arg("-map")
+        .arg("[outv]");
+    if options.music.is_some() {
+        command.arg("-map").arg("[outa]").arg("-c:a").arg("aac");
+    }
+    command
+        .arg("-c:v")
+        .arg("libx264")
+        .arg("-t")
+        .arg(length.to_string())
+        .arg("-y")
+        .arg(output);
+
+    run_ffmpeg_with_progress(command, Some(length), callback)
+}
+
+/// Appearance overrides for burned-in subtitles, applied as an ASS
+/// `force_style`. Unset fields keep the subtitle file's own styling.
+#[derive(Debug, Clone, Default)]
+pub struct SubtitleStyle {
+    pub font: Option<String>,
+    pub font_size: Option<u32>,
+    /// `#RRGGBB` or a basic colour name.
+    pub color: Option<String>,
+    pub outline_color: Option<String>,
+    /// Outline width in pixels.
+    pub outline: Option<f64>,
+}
+
+impl SubtitleStyle {
+    fn force_style(&self) -> Result<Option<String>> {
+        let mut fields = Vec::new();
+        if let Some(font) = &self.font {
+            fields.push(format!("FontName={}", font));
+        }
+        if let Some(size) = self.font_size {
+            fields.push(format!("FontSize={}", size));
+        }
+        if let Some(color) = &self.color {
+            fields.push(format!("PrimaryColour={}", ass_colour(color)?));
+        }
+        if let Some(color) = &self.outline_color {
+            fields.push(format!("OutlineColour={}", ass_colour(color)?));
+        }
+        if let Some(outline) = self.outline {
+            fields.push(format!("BorderStyle=1,Outline={}", outline));
+        }
+        Ok((!fields.is_empty()).then(|| fields.join(",")))
+    }
+}
+
+/// Converts `#RRGGBB` or a colour name to ASS's `&HAABBGGRR` notation.
+fn ass_colour(value: &str) -> Result<String> {
+    let hex = match value.trim().to_ascii_lowercase().as_str() {
+        "white" => "ffffff".to_string(),
+        "black" => "000000".to_string(),
+        "yellow" => "ffff00".to_string(),
+        "red" => "ff0000".to_string(),
+        "green" => "00ff00".to_string(),
+        "blue" => "0000ff".to_string(),
+        "cyan" => "00ffff".to_string(),
+        "magenta" => "ff00ff".to_string(),
+        other => other.trim_start_matches('#').to_string(),
+    };
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!(
+            "Invalid colour '{}': use #RRGGBB or a name like white or yellow",
+            value
+        ));
+    }
+    Ok(format!("&H00{}{}{}", &hex[4..6], &hex[2..4], &hex[0..2]).to_ascii_uppercase())
+}
+
+/// Escapes a filter option value, then the filtergraph around it, so paths
+/// with `:`, `'`, `\`, `,`, `[` or `]` (e.g. `C:\Subs\ep 1.srt`) reach the
+/// filter intact. No shell is involved, so there is no third level.
+fn escape_filter_arg(value: &str) -> String {
+    let mut option = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::with_capacity(option.len());
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+/// Renders a subtitle file into the picture. The video is re-encoded and the
+/// audio copied.
+pub fn burn_subtitles<F>(
+    video: &Path,
+    subtitles: &Path,
+    output: &Path,
+    style: &SubtitleStyle,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    SubtitleFormat::from_path(subtitles)?;
+    let info = probe::probe(video)?;
+    if info.video().is_none() {
+        return Err(anyhow!("{} has no video stream", video.display()));
+    }
+
+    let mut filter = format!(
+        "subtitles=filename={}",
+        escape_filter_arg(&subtitles.to_string_lossy())
+    );
+    if let Some(force_style) = style.force_style()? {
+        write!(filter, ":force_style={}", escape_filter_arg(&force_style)).unwrap();
+    }
+
+    callback(ProgressInfo::Log(format!(
+        "Burning in {}...",
+        subtitles.display()
+    )));
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
+        .arg(video)
+        .arg("-map")
+        .arg("0:v:0")
+        .arg("-map")
+        .arg("0:a?")
+        .arg("-vf")
+        .arg(&filter)
+        .arg("-c:v")
+        .arg("libx264")
+        .arg("-c:a")
+        .arg("copy")
+        .arg("-y")
+        .arg(output);
+
+    run_ffmpeg_with_progress(command, info.duration, callback)
+}
+
+/// Adds subtitle files as selectable tracks without touching the picture.
+/// `languages` holds an ISO 639 code per file (or is empty). MP4/MOV get
+/// mov_text, MKV keeps SubRip/ASS as they are and WebM gets WebVTT.
+pub fn mux_subtitles<F>(
+    video: &Path,
+    subtitles: &[PathBuf],
+    output: &Path,
+    languages: &[String],
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    if subtitles.is_empty() {
+        return Err(anyhow!("No subtitle files provided"));
+    }
+    if !languages.is_empty() && languages.len() != subtitles.len() {
+        return Err(anyhow!(
+            "Got {} languages for {} subtitle files; give one per file",
+            languages.len(),
+            subtitles.len()
+        ));
+    }
+    if let Some(bad) = languages
+        .iter()
+        .find(|l| !(2..=3).contains(&l.len()) || !l.chars().all(|c| c.is_ascii_alphabetic()))
+    {
+        return Err(anyhow!(
+            "Invalid language '{}': use an ISO 639 code like eng or de",
+            bad
+        ));
+    }
+
+    let container = lowercase_extension(output);
+    let codecs = subtitles
+        .iter()
+        .map(|sub| {
+            let format = SubtitleFormat::from_path(sub)?;
+            Ok(match container.as_str() {
+                "mp4" | "m4v" | "mov" => "mov_text",
+                "mkv" => match format {
+                    SubtitleFormat::Ass => "ass",
+                    SubtitleFormat::WebVtt => "webvtt",
+                    SubtitleFormat::Srt => "subrip",
+                },
+                "webm" => "webvtt",
+                _ => {
+                    return Err(anyhow!(
+                        "Soft subtitles need an .mp4, .m4v, .mov, .mkv or .webm output"
+                    ))
+                }
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let info = probe::probe(video)?;
+    // New tracks are numbered after the subtitle streams already present.
+    let existing = info
+        .streams
+        .iter()
+        .filter(|s| s.kind == probe::StreamKind::Subtitle)
+        .count();
+
+    callback(ProgressInfo::Log(format!(
+        "Adding {} subtitle track(s)...",
+        subtitles.len()
+    )));
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-i").arg(video);
+    for sub in subtitles {
+        command.arg("-i").arg(sub);
+    }
+    command.arg("-map").arg("0");
+    for i in 0..subtitles.len() {
+        command.arg("-map").arg((i + 1).to_string());
+    }
+    command.arg("-c").arg("copy");
+    if matches!(container.as_str(), "mp4" | "m4v" | "mov") {
+        // MP4 can only hold mov_text, so existing text tracks are converted too.
+        command.arg("-c:s").arg("mov_text");
+    }
+    for (i, codec) in codecs.iter().enumerate() {
+        let index = existing + i;
+        command.arg(format!("-c:s:{}", index)).arg(codec);
+        if let Some(lang) = languages.get(i) {
+            command
+                .arg(format!("-metadata:s:s:{}", index))
+                .arg(format!("language={}", lang.to_ascii_lowercase()));
+        }
+    }
+    command.arg("-y").arg(output);
+
+    run_ffmpeg_with_progress(command, info.duration, callback)
+}
+
+/// Converts a subtitle file between SRT, WebVTT and ASS (by extension) and
+/// applies `timing` on the way.
+pub fn convert_subtitles<F>(
+    input: &Path,
+    output: &Path,
+    timing: &Retime,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let format = SubtitleFormat::from_path(input)?;
+    let cues = subtitle::read(input, format)?;
+    let retimed = subtitle::retime(&cues, timing)?;
+    // Only the source's styling is lost; plain SRT/VTT text into ASS is fine.
+    if format == SubtitleFormat::Ass {
+        callback(ProgressInfo::Log(
+            "Note: ASS styles and override tags are not carried over.".to_string(),
+        ));
+    }
+    subtitle::save(output, &retimed)?;
+    callback(ProgressInfo::Log(format!(
+        "Wrote {} of {} cues to {}",
+        retimed.len(),
+        cues.len(),
+        output.display()
+    )));
+    callback(ProgressInfo::Percentage(1.0));
+    Ok(())
+}
+
+/// Writes one embedded subtitle stream to an SRT, WebVTT or ASS file.
+/// Bitmap subtitles (PGS, VobSub, DVB) can't be turned into text.
+pub fn extract_subtitles<F>(
+    input: &Path,
+    output: &Path,
+    stream: usize,
+    mut callback: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressInfo),
+{
+    let info = probe::probe(input)?;
+    let codec = nth_stream(&info, probe::StreamKind::Subtitle, stream)?
+        .codec
+        .clone()
+        .unwrap_or_default();
+    if matches!(
+        codec.as_str(),
+        "hdmv_pgs_subtitle" | "dvd_subtitle" | "dvb_subtitle" | "xsub"
+    ) {
+        return Err(anyhow!(
+            "Subtitle stream {} is a bitmap format ({}) and can't be saved as text",
+            stream,
+            codec
+        ));
+    }
+    let encoder = match SubtitleFormat::from_path(output)? {
+        SubtitleFormat::Srt => "srt",
+        SubtitleFormat::WebVtt => "webvtt",
+        SubtitleFormat::Ass => "ass",
+    };
+
+    callback(ProgressInfo::Log(format!(
+        "Extracting subtitle stream {} ({})...",
+        stream, codec
+    )));
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg(format!("0:s:{}", stream))
+        .arg("-c:s")
+        .arg(encoder)
+        .arg("-y")
+        .arg(output);
+
+    run_ffmpeg_with_progress(command, info.duration, callback)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_filter_arg_windows_path() {
+        assert_eq!(
+            escape_filter_arg(r"C:\Subs\ep 1.srt"),
+            r"C\\:\\\\Subs\\\\ep 1.srt"
+        );
+    }
+
+    #[test]
+    fn escape_filter_arg_quote() {
+        assert_eq!(escape_filter_arg("it's.srt"), r"it\\\'s.srt");
+    }
+
+    #[test]
+    fn escape_filter_arg_graph_specials() {
+        assert_eq!(
+            escape_filter_arg("/tmp/a,b [1];c.srt"),
+            r"/tmp/a\,b \[1\]\;c.srt"
+        );
+    }
+
+    #[test]
+    fn escape_filter_arg_plain_path_unchanged() {
+        assert_eq!(escape_filter_arg("/tmp/subs/ep1.srt"), "/tmp/subs/ep1.srt");
+    }
+
+    #[test]
+    fn ass_colour_hex_is_bgr() {
+        assert_eq!(ass_colour("#FF8000").unwrap(), "&H000080FF");
+        assert_eq!(ass_colour("00ff00").unwrap(), "&H0000FF00");
+    }
+
+    #[test]
+    fn ass_colour_names() {
+        assert_eq!(ass_colour("white").unwrap(), "&H00FFFFFF");
+        assert_eq!(ass_colour("Yellow").unwrap(), "&H0000FFFF");
+    }
+
+    #[test]
+    fn ass_colour_rejects_invalid() {
+        assert!(ass_colour("#FFF").is_err());
+        assert!(ass_colour("orange").is_err());
+        assert!(ass_colour("#GG0000").is_err());
+    }
+
+    #[test]
+    fn capped_fps_keeps_unknown_source_rate() {
+        assert_eq!(capped_fps(None, Some(30.0)), None);
+        assert_eq!(capped_fps(Some(60.0), Some(30.0)), Some(30.0));
+        assert_eq!(capped_fps(Some(24.0), Some(30.0)), None);
+    }
+
+    #[test]
+    fn max_bounds_reject_zero() {
+        assert!(parse_max_size("0x720").is_err());
+        assert!(parse_max_size("x0").is_err());
+        assert_eq!(parse_max_size("1280x").unwrap(), (Some(1280), None));
+        assert!(parse_max_fps("0").is_err());
+        assert!(parse_max_fps("-5").is_err());
+        assert!(parse_max_fps("abc").is_err());
+        assert_eq!(parse_max_fps(" ").unwrap(), None);
+        assert_eq!(parse_max_fps("29.97").unwrap(), Some(29.97));
+    }
+
+    #[test]
+    fn parse_volume_factors_and_gains() {
+        assert_eq!(parse_volume("0.2").unwrap(), "0.2");
+        assert_eq!(parse_volume("-6dB").unwrap(), "-6dB");
+        assert_eq!(parse_volume("3.5db").unwrap(), "3.5dB");
+        assert!(parse_volume("-0.5").is_err());
+        assert!(parse_volume("loud").is_err());
+        assert!(parse_volume("dB").is_err());
+    }
+
+    #[test]
+    fn remove_passlogs_leaves_other_runs() {
+        let dir = std::env::temp_dir().join(format!("framix_passlog_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in [
+            "run_12-0.log",
+            "run_12-0.log.mbtree",
+            "run_12.log.cutree",
+            "run_123-0.log",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        remove_passlogs(&dir.join("run_12"));
+
+        let mut left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(left, ["run_123-0.log"]);
+    }
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = ["img10.jpg", "IMG2.jpg", "img1.jpg", "img02.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["img1.jpg", "IMG2.jpg", "img02.jpg", "img10.jpg"]);
+    }
+
+    #[test]
+    fn range_length_checks_and_clamps() {
+        assert_eq!(range_length(60.0, 10.0, None, None).unwrap(), 50.0);
+        assert_eq!(range_length(60.0, 10.0, Some(25.0), None).unwrap(), 15.0);
+        assert_eq!(range_length(60.0, 50.0, None, Some(30.0)).unwrap(), 10.0);
+        assert!(range_length(60.0, 10.0, Some(5.0), None).is_err());
+        assert!(range_length(60.0, 10.0, Some(20.0), Some(5.0)).is_err());
+        assert!(range_length(60.0, 10.0, None, Some(0.0)).is_err());
+        assert!(range_length(60.0, 60.0, None, None).is_err());
+    }
+
+    #[test]
+    fn loudnorm_stats_from_log_takes_last_json_block() {
+        let lines: Vec<String> = r#"[Parsed_loudnorm_0 @ 0x1] 
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "18.06",
+	"input_thresh" : "-39.20",
+	"output_i" : "-14.02",
+	"output_tp" : "-1.00",
+	"output_lra" : "11.20",
+	"output_thresh" : "-24.95",
+	"normalization_type" : "linear",
+	"target_offset" : "0.02"
+}"#
+        .lines()
+        .map(str::to_string)
+        .collect();
+        let stats = LoudnormStats::from_log(&lines).unwrap();
+        assert_eq!(stats.input_i, "-27.61");
+        assert_eq!(stats.output_tp, "-1.00");
+        assert_eq!(stats.target_offset, "0.02");
+        assert!(LoudnormStats::from_log(&["no stats here".to_string()]).is_err());
+    }
+
+    #[test]
+    fn loudness_target_presets_and_values() {
+        assert_eq!(
+            "podcast".parse::<LoudnessTarget>().unwrap().integrated,
+            -16.0
+        );
+        assert_eq!("EBU".parse::<LoudnessTarget>().unwrap().lra, 15.0);
+        assert_eq!(
+            "-18lufs".parse::<LoudnessTarget>().unwrap().integrated,
+            -18.0
+        );
+        assert!("-2".parse::<LoudnessTarget>().is_err());
+        assert!("loud".parse::<LoudnessTarget>().is_err());
+    }
+
+    #[test]
+    fn normalize_output_containers() {
+        assert_eq!(audio_encoder_for(Path::new("a.ogg")).unwrap(), "libopus");
+        assert_eq!(audio_encoder_for(Path::new("a.WAV")).unwrap(), "pcm_s16le");
+        assert!(audio_encoder_for(Path::new("a.xyz")).is_err());
+        assert!(holds_video(Path::new("a.webm")));
+        assert!(!holds_video(Path::new("a.m4a")));
+    }
+
+    #[test]
+    fn capped_dimensions_keep_aspect_and_even_sizes() {
+        assert_eq!(capped_dimensions(1920, 1080, Some(1280), None), (1280, 720));
+        assert_eq!(capped_dimensions(1080, 1920, None, Some(1280)), (720, 1280));
+        assert_eq!(
+            capped_dimensions(1280, 720, Some(1920), Some(1080)),
+            (1280, 720)
+        );
+        assert_eq!(capped_dimensions(1001, 1001, Some(501), None), (500, 500));
+    }
+
+    #[test]
+    fn timelapse_method_names() {
+        assert_eq!(
+            "Sample".parse::<TimelapseMethod>().unwrap(),
+            TimelapseMethod::Sample
+        );
+        assert_eq!(
+            "tmix".parse::<TimelapseMethod>().unwrap(),
+            TimelapseMethod::Blend
+        );
+        assert!("drop".parse::<TimelapseMethod>().is_err());
+    }
+
+    #[test]
+    fn sampling_filter_keeps_last_frame_of_blended_groups() {
+        assert_eq!(
+            sampling_filter(8, false),
+            r"select='eq(mod(n\,8)\,0)',setpts=(PTS-STARTPTS)/8"
+        );
+        assert_eq!(
+            sampling_filter(8, true),
+            r"tmix=frames=8,select='eq(mod(n\,8)\,7)',setpts=(PTS-STARTPTS)/8"
+        );
+    }
+
+    #[test]
+    fn timelapse_checks_speed_before_probing() {
+        let missing = Path::new("does-not-exist.mp4");
+        let run = |options: TimelapseOptions| {
+            timelapse(missing, Path::new("out.mp4"), &options, |_| {})
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(run(TimelapseOptions {
+            speed: 0.0,
+            ..Default::default()
+        })
+        .contains("greater than zero"));
+        assert!(run(TimelapseOptions {
+            speed: 2.0,
+            interpolate: true,
+            ..Default::default()
+        })
+        .contains("slow motion"));
+    }
+
+    fn segment(start: f64, end: Option<f64>, speed: f64) -> SpeedSegment {
+        SpeedSegment { start, end, speed }
+    }
+
+    #[test]
+    fn parse_speed_ramp_sorts_segments() {
+        assert_eq!(
+            parse_speed_ramp("1:00-@0.5, 0:10-1:00@8").unwrap(),
+            [segment(10.0, Some(60.0), 8.0), segment(60.0, None, 0.5)]
+        );
+        assert_eq!(
+            parse_speed_ramp("-5@2").unwrap(),
+            [segment(0.0, Some(5.0), 2.0)]
+        );
+    }
+
+    #[test]
+    fn parse_speed_ramp_rejects_bad_segments() {
+        assert!(parse_speed_ramp("10-60").is_err());
+        assert!(parse_speed_ramp("10@8").is_err());
+        assert!(parse_speed_ramp("10-60@0").is_err());
+        assert!(parse_speed_ramp("60-10@8").is_err());
+        assert!(parse_speed_ramp("0-30@2,20-40@4").is_err());
+        // An open-ended segment overlaps anything after it, even when given
+        // out of order.
+        assert!(parse_speed_ramp("50-60@2,10-@4").is_err());
+        assert!(parse_speed_ramp("0-10@2,10-20@4").is_ok());
+    }
+
+    #[test]
+    fn ramp_pieces_fill_gaps_at_normal_speed() {
+        let ramp = parse_speed_ramp("10-20@4,30-@2").unwrap();
+        assert_eq!(
+            ramp_pieces(&ramp, 40.0),
+            [
+                (0.0, 10.0, 1.0),
+                (10.0, 20.0, 4.0),
+                (20.0, 30.0, 1.0),
+                (30.0, 40.0, 2.0)
+            ]
+        );
+        // Segments beyond the end are clipped, and a tail gap is filled.
+        let ramp = parse_speed_ramp("5-50@3,60-70@2").unwrap();
+        assert_eq!(
+            ramp_pieces(&ramp, 20.0),
+            [(0.0, 5.0, 1.0), (5.0, 20.0, 3.0)]
+        );
+        let ramp = parse_speed_ramp("0-5@3").unwrap();
+        assert_eq!(ramp_pieces(&ramp, 8.0), [(0.0, 5.0, 3.0), (5.0, 8.0, 1.0)]);
+    }
+
+    fn fade(duration: f64) -> Option<Transition> {
+        Some(Transition {
+            kind: "fade".to_string(),
+            duration,
+        })
+    }
+
+    #[test]
+    fn parse_transitions_entries() {
+        assert_eq!(
+            parse_transitions("Fade, cut, wipeleft:0.5", 1.0).unwrap(),
+            [
+                fade(1.0),
+                None,
+                Some(Transition {
+                    kind: "wipeleft".to_string(),
+                    duration: 0.5
+                })
+            ]
+        );
+        assert!(parse_transitions("", 1.0).unwrap().is_empty());
+        assert!(parse_transitions("spin", 1.0).is_err());
+        assert!(parse_transitions("fade:0", 1.0).is_err());
+        assert!(parse_transitions("fade:abc", 1.0).is_err());
+    }
+
+    #[test]
+    fn transitions_per_join_counts() {
+        assert_eq!(transitions_per_join(&[], 2).unwrap(), [None, None]);
+        assert_eq!(
+            transitions_per_join(&[fade(1.0)], 3).unwrap(),
+            [fade(1.0), fade(1.0), fade(1.0)]
+        );
+        assert_eq!(
+            transitions_per_join(&[None, fade(2.0)], 2).unwrap(),
+            [None, fade(2.0)]
+        );
+        let err = transitions_per_join(&[None, None], 3).unwrap_err();
+        assert!(err.to_string().contains("Got 2 transitions for 3 joins"));
+    }
+
+    #[test]
+    fn transition_offsets_subtract_earlier_overlaps() {
+        let mut filter = String::new();
+        let length = write_transition_chain(
+            &mut filter,
+            &["a", "b", "c", "d"],
+            &[10.0, 8.0, 6.0, 5.0],
+            &[fade(1.0), None, fade(2.0)],
+            false,
+        )
+        .unwrap();
+        // 10 + 8 - 1 + 6 + 5 - 2
+        assert_eq!(length, 26.0);
+        assert!(filter.contains("[v0][v1]xfade=transition=fade:duration=1:offset=9[vx1];"));
+        assert!(filter.contains("[vx1][v2]concat=n=2:v=1:a=0[vx2];"));
+        assert!(filter.ends_with("[vx2][v3]xfade=transition=fade:duration=2:offset=21[outv]"));
+        assert!(!filter.contains("acrossfade"));
+    }
+
+    #[test]
+    fn transition_longer_than_clip_is_rejected() {
+        let mut filter = String::new();
+        let err =
+            write_transition_chain(&mut filter, &["a", "b"], &[10.0, 1.5], &[fade(2.0)], true)
+                .unwrap_err();
+        assert!(err.to_string().contains("longer than the clips"));
+    }
+
+    #[test]
+    fn collect_images_sorts_folders_and_keeps_files() {
+        let dir = std::env::temp_dir().join(format!("framix_slides_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["img10.jpg", "img2.PNG", "img1.jpeg", "notes.txt"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let empty = dir.join("empty");
+        std::fs::create_dir_all(&empty).unwrap();
+
+        let cover = PathBuf::from("cover.png");
+        let images = collect_images(&[cover.clone(), dir.clone()]);
+        let no_images = collect_images(&[empty]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = images
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["cover.png", "img1.jpeg", "img2.PNG", "img10.jpg"]);
+        assert!(no_images.is_err());
+        assert!(collect_images(&[]).is_err());
+    }
+
+    #[test]
+    fn slide_fit_filters() {
+        assert_eq!("crop".parse::<SlideFit>().unwrap(), SlideFit::Cover);
+        assert!("stretch".parse::<SlideFit>().is_err());
+        assert_eq!(
+            SlideFit::Contain.filter(1280, 720),
+            "scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2"
+        );
+        assert_eq!(
+            SlideFit::Cover.filter(1280, 720),
+            "scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720"
+        );
+    }
+
+    #[test]
+    fn counters_in_output_templates() {
+        assert!(validate_counter_pattern("thumb_%02d.jpg", "x").is_ok());
+        assert!(validate_counter_pattern("part_%d.mp4", "x").is_ok());
+        assert!(validate_counter_pattern("thumb.jpg", "thumb_%02d.jpg")
+            .unwrap_err()
+            .to_string()
+            .contains("thumb_%02d.jpg"));
+        assert_eq!(expand_counter("thumb_%03d.jpg", 7), "thumb_007.jpg");
+        assert_eq!(expand_counter("thumb_%d.jpg", 12), "thumb_12.jpg");
+        assert_eq!(expand_counter("cover.jpg", 1), "cover.jpg");
+    }
+
+    #[test]
+    fn parse_grid_sizes() {
+        assert_eq!(parse_grid("4X3").unwrap(), (4, 3));
+        assert!(parse_grid("4x0").is_err());
+        assert!(parse_grid("4").is_err());
+        assert!(parse_grid("ax3").is_err());
+    }
+
+    #[test]
+    fn extensions_ignore_case() {
+        assert_eq!(lowercase_extension(Path::new("Clip.MP4")), "mp4");
+        assert_eq!(lowercase_extension(Path::new("README")), "");
+        assert!(has_extension(Path::new("a.WAV"), "wav"));
+        assert!(!has_extension(Path::new("a.wave"), "wav"));
+    }
+
+    #[test]
+    fn extract_frames_checks_output_before_probing() {
+        let missing = Path::new("does-not-exist.mp4");
+        let run = |template: &str, jpeg_quality: u8| {
+            let options = FramesOptions {
+                jpeg_quality,
+                ..Default::default()
+            };
+            extract_frames(missing, template, &options, |_| {})
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(run("frame.png", 90).contains("frame_%05d.png"));
+        assert!(run("frame_%05d.bmp", 90).contains(".png, .jpg or .jpeg"));
+        assert!(run("frame_%05d.jpg", 0).contains("between 1 and 100"));
+    }
+
+    #[test]
+    fn jpeg_quality_maps_to_qscale() {
+        assert_eq!(jpeg_qscale(100), 2);
+        assert_eq!(jpeg_qscale(1), 31);
+        assert!(jpeg_qscale(90) < jpeg_qscale(50));
+    }
+
+    fn atempo_stages(speed: f64) -> Vec<f64> {
+        atempo_chain(speed)
+            .split(',')
+            .map(|stage| stage.strip_prefix("atempo=").unwrap().parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn atempo_chain_splits_into_valid_stages() {
+        assert_eq!(atempo_stages(8.0), [2.0, 2.0, 2.0]);
+        assert_eq!(atempo_stages(1.0), [1.0]);
+        assert_eq!(atempo_stages(0.2), [0.5, 0.5, 0.8]);
+        for speed in [0.1, 0.2, 0.5, 1.0, 1.5, 3.0, 8.0, 100.0] {
+            let stages = atempo_stages(speed);
+            assert!(
+                stages.iter().all(|s| (0.5..=2.0).contains(s)),
+                "{:?}",
+                stages
+            );
+            let product: f64 = stages.iter().product();
+            assert!((product - speed).abs() < 1e-9, "{} != {}", product, speed);
+        }
+    }
+
+    #[test]
+    fn timelapse_audio_modes() {
+        assert_eq!(
+            "none".parse::<TimelapseAudio>().unwrap(),
+            TimelapseAudio::Drop
+        );
+        assert_eq!(
+            "preserve-pitch".parse::<TimelapseAudio>().unwrap(),
+            TimelapseAudio::PreservePitch
+        );
+        assert!("loud".parse::<TimelapseAudio>().is_err());
+    }
+
+    #[test]
+    fn parse_timestamp_formats() {
+        assert_eq!(parse_timestamp("90").unwrap(), 90.0);
+        assert_eq!(parse_timestamp(" 12.5 ").unwrap(), 12.5);
+        assert_eq!(parse_timestamp("1:30").unwrap(), 90.0);
+        assert_eq!(parse_timestamp("01:02:03.5").unwrap(), 3723.5);
+        assert!(parse_timestamp("1:2:3:4").is_err());
+        assert!(parse_timestamp("1:xx").is_err());
+        assert!(parse_timestamp("-5").is_err());
+        assert!(parse_timestamp("").is_err());
+    }
+
+    #[test]
+    fn parse_timestamp_rejects_non_finite() {
+        for value in ["nan", "NaN", "inf", "-inf", "infinity", "1:inf"] {
+            assert!(parse_timestamp(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn wav_copies_only_little_endian_interleaved_pcm() {
+        for codec in ["pcm_s16le", "pcm_s24le", "pcm_f32le", "pcm_u8", "pcm_mulaw"] {
+            assert!(AudioFormat::Wav.can_copy(codec), "{}", codec);
+        }
+        for codec in [
+            "pcm_s16be",
+            "pcm_s24be",
+            "pcm_f32be",
+            "pcm_s16le_planar",
+            "aac",
+        ] {
+            assert!(!AudioFormat::Wav.can_copy(codec), "{}", codec);
+        }
+        assert!(AudioFormat::Aac.can_copy("aac"));
+        assert!(!AudioFormat::Mp3.can_copy("aac"));
+    }
+
+    #[test]
+    fn audio_format_from_extension() {
+        assert_eq!(
+            AudioFormat::from_path(Path::new("a.M4A")).unwrap(),
+            AudioFormat::Aac
+        );
+        assert_eq!(
+            AudioFormat::from_path(Path::new("a.opus")).unwrap(),
+            AudioFormat::Opus
+        );
+        assert!(AudioFormat::from_path(Path::new("a.mp4")).is_err());
+        assert!(container_accepts_audio(Path::new("a.mkv"), "pcm_s16be"));
+        assert!(!container_accepts_audio(Path::new("a.webm"), "aac"));
+    }
+
+    #[test]
+    fn split_video_checks_before_running() {
+        let run = |template: &str, mode: SplitMode| {
+            split_video(Path::new("in.mp4"), template, &mode, false, |_| {})
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(run("part.mp4", SplitMode::Every(10.0)).contains("part_%03d.mp4"));
+        assert!(run("part_%03d.mp4", SplitMode::Every(0.0)).contains("greater than zero"));
+        assert!(run("part_%03d.mp4", SplitMode::At(vec![0.0])).contains("No cut points"));
+    }
+
+    fn stream(kind: probe::StreamKind, codec: &str) -> probe::StreamInfo {
+        probe::StreamInfo {
+            index: 0,
+            kind,
+            codec: Some(codec.to_string()),
+            codec_long_name: None,
+            profile: None,
+            width: None,
+            height: None,
+            pix_fmt: None,
+            fps: None,
+            time_base: None,
+            sample_rate: None,
+            channels: None,
+            channel_layout: None,
+            bit_rate: None,
+            duration: None,
+            attached_pic: false,
+            rotation: 0,
+            tags: Default::default(),
+        }
+    }
+
+    fn video_stream(width: u32, height: u32, fps: f64) -> probe::StreamInfo {
+        probe::StreamInfo {
+            width: Some(width),
+            height: Some(height),
+            fps: Some(fps),
+            pix_fmt: Some("yuv420p".to_string()),
+            time_base: Some("1/15360".to_string()),
+            ..stream(probe::StreamKind::Video, "h264")
+        }
+    }
+
+    fn audio_stream(sample_rate: u32) -> probe::StreamInfo {
+        probe::StreamInfo {
+            index: 1,
+            sample_rate: Some(sample_rate),
+            channels: Some(2),
+            channel_layout: Some("stereo".to_string()),
+            ..stream(probe::StreamKind::Audio, "aac")
+        }
+    }
+
+    fn media(streams: Vec<probe::StreamInfo>) -> MediaInfo {
+        MediaInfo {
+            path: "clip.mp4".to_string(),
+            format: None,
+            format_long_name: None,
+            duration: Some(10.0),
+            bit_rate: None,
+            size: None,
+            tags: Default::default(),
+            streams,
+            chapters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn combine_target_parses() {
+        assert_eq!(
+            "first".parse::<CombineTarget>().unwrap(),
+            CombineTarget::First
+        );
+        assert_eq!(
+            " Largest ".parse::<CombineTarget>().unwrap(),
+            CombineTarget::Largest
+        );
+        assert_eq!(
+            "1920x1080@29.97".parse::<CombineTarget>().unwrap(),
+            CombineTarget::Explicit {
+                width: 1920,
+                height: 1080,
+                fps: 29.97
+            }
+        );
+        for bad in ["0x720@30", "1280x720@0", "1280x720", "1280@30", "huge"] {
+            assert!(bad.parse::<CombineTarget>().is_err(), "{} accepted", bad);
+        }
+    }
+
+    #[test]
+    fn resolve_canvas_picks_largest_and_evens_size() {
+        let infos = [
+            media(vec![video_stream(1280, 720, 25.0), audio_stream(44100)]),
+            media(vec![video_stream(1919, 1081, 30.0), audio_stream(48000)]),
+        ];
+        let canvas = resolve_canvas(CombineTarget::Largest, &infos).unwrap();
+        assert_eq!((canvas.width, canvas.height), (1920, 1082));
+        assert_eq!((canvas.fps, canvas.sample_rate), (30.0, 48000));
+
+        let canvas = resolve_canvas(CombineTarget::First, &infos).unwrap();
+        assert_eq!((canvas.width, canvas.height), (1280, 720));
+        assert_eq!((canvas.fps, canvas.sample_rate), (25.0, 44100));
+    }
+
+    #[test]
+    fn resolve_canvas_needs_video() {
+        let infos = [media(vec![audio_stream(48000)])];
+        assert!(resolve_canvas(CombineTarget::First, &infos).is_err());
+        assert!(resolve_canvas(CombineTarget::Largest, &infos).is_err());
+    }
+
+    #[test]
+    fn combine_mode_parses() {
+        assert_eq!("auto".parse::<CombineMode>().unwrap(), CombineMode::Auto);
+        assert_eq!("COPY".parse::<CombineMode>().unwrap(), CombineMode::Copy);
+        assert_eq!(
+            "re-encode".parse::<CombineMode>().unwrap(),
+            CombineMode::Reencode
+        );
+        assert!("transcode".parse::<CombineMode>().is_err());
+    }
+
+    #[test]
+    fn streams_match_compares_signatures() {
+        let clip = || media(vec![video_stream(1920, 1080, 30.0), audio_stream(48000)]);
+        assert!(streams_match(&[clip(), clip()]));
+
+        let resized = media(vec![video_stream(1280, 720, 30.0), audio_stream(48000)]);
+        assert!(!streams_match(&[clip(), resized]));
+
+        let resampled = media(vec![video_stream(1920, 1080, 30.0), audio_stream(44100)]);
+        assert!(!streams_match(&[clip(), resampled]));
+
+        let silent = media(vec![video_stream(1920, 1080, 30.0)]);
+        assert!(!streams_match(&[clip(), silent]));
+
+        let audio_only = || media(vec![audio_stream(48000)]);
+        assert!(!streams_match(&[audio_only(), audio_only()]));
+    }
+
+    #[test]
+    fn clip_filters_fill_missing_audio_with_silence() {
+        let canvas = Canvas {
+            width: 1280,
+            height: 720,
+            fps: 30.0,
+            sample_rate: 48000,
+        };
+        let mut silent = media(vec![video_stream(1280, 720, 30.0)]);
+        silent.duration = Some(4.5);
+        let infos = [
+            media(vec![video_stream(1920, 1080, 30.0), audio_stream(44100)]),
+            silent,
+        ];
+
+        let mut filter = String::new();
+        write_clip_filters(&mut filter, &infos, &canvas, true).unwrap();
+        assert!(filter.contains("[0:a]aresample=48000,"));
+        assert!(filter.contains("apad,atrim=duration=10[a0];"));
+        assert!(filter.contains("anullsrc=r=48000:cl=stereo,atrim=duration=4.5,"));
+        assert!(filter.ends_with("[a1];"));
+        assert!(!filter.contains("[1:a]"));
+
+        let mut filter = String::new();
+        write_clip_filters(&mut filter, &infos, &canvas, false).unwrap();
+        assert!(!filter.contains("anullsrc") && !filter.contains(":a]"));
+        assert!(filter.contains("[v1];"));
+    }
+
+    #[test]
+    fn resolve_container_defaults_audio_per_container() {
+        let resolve = |out: &str, codec| resolve_container(Path::new(out), codec, None, None);
+        assert_eq!(resolve("a.mp4", VideoCodec::H264).unwrap(), AudioCodec::Aac);
+        assert_eq!(resolve("a.MKV", VideoCodec::Vp9).unwrap(), AudioCodec::Aac);
+        assert_eq!(
+            resolve("a.webm", VideoCodec::Vp9).unwrap(),
+            AudioCodec::Opus
+        );
+        // Unknown containers are left to ffmpeg.
+        assert_eq!(resolve("a.avi", VideoCodec::H265).unwrap(), AudioCodec::Aac);
+    }
+
+    #[test]
+    fn resolve_container_rejects_mismatches() {
+        let out = Path::new("a.webm");
+        let err = resolve_container(out, VideoCodec::H264, None, None).unwrap_err();
+        assert!(err.to_string().contains("can't hold"), "{}", err);
+        assert!(resolve_container(out, VideoCodec::Vp9, Some(AudioCodec::Aac), None).is_err());
+        assert!(resolve_container(Path::new("a.mov"), VideoCodec::Vp9, None, None).is_err());
+        assert_eq!(
+            resolve_container(out, VideoCodec::Av1, Some(AudioCodec::None), None).unwrap(),
+            AudioCodec::None
+        );
+    }
+
+    #[test]
+    fn resolve_container_checks_copied_audio() {
+        let copy = Some(AudioCodec::Copy);
+        let webm = Path::new("a.webm");
+        assert!(resolve_container(webm, VideoCodec::Vp9, copy, Some("aac")).is_err());
+        assert!(resolve_container(webm, VideoCodec::Vp9, copy, Some("opus")).is_ok());
+        let mp4 = Path::new("a.mp4");
+        assert!(resolve_container(mp4, VideoCodec::H264, copy, Some("ac3")).is_ok());
+        assert!(resolve_container(mp4, VideoCodec::H264, copy, Some("vorbis")).is_err());
+        // Nothing to check when the source has no audio.
+        assert!(resolve_container(mp4, VideoCodec::H264, copy, None).is_ok());
+    }
+
+    #[test]
+    fn music_chain_stops_with_short_track() {
+        let options = MusicOptions {
+            music_start: 5.0,
+            fade_in: 2.0,
+            fade_out: 3.0,
+            ..MusicOptions::default()
+        };
+        let (chain, length) = music_chain(&options, 60.0, 25.0).unwrap();
+        assert_eq!(length, 20.0);
+        assert_eq!(
+            chain,
+            "atrim=0:20,asetpts=PTS-STARTPTS,afade=t=in:st=0:d=2,afade=t=out:st=17:d=3"
+        );
+    }
+
+    #[test]
+    fn music_chain_loops_and_delays_to_video_end() {
+        let options = MusicOptions {
+            loop_music: true,
+            delay: 1.5,
+            fade_out: 90.0,
+            ..MusicOptions::default()
+        };
+        let (chain, length) = music_chain(&options, 60.0, 25.0).unwrap();
+        assert_eq!(length, 58.5);
+        // Fades longer than the music are clamped to its length.
+        assert_eq!(
+            chain,
+            "atrim=0:58.5,asetpts=PTS-STARTPTS,afade=t=out:st=0:d=58.5,adelay=1500:all=1"
+        );
+    }
+
+    #[test]
+    fn music_chain_rejects_offsets_past_the_end() {
+        let late = MusicOptions {
+            delay: 60.0,
+            ..MusicOptions::default()
+        };
+        assert!(music_chain(&late, 60.0, 25.0).is_err());
+        let skipped = MusicOptions {
+            music_start: 25.0,
+            ..MusicOptions::default()
+        };
+        assert!(music_chain(&skipped, 60.0, 25.0).is_err());
+    }
+
+    #[test]
+    fn ducking_presets_parse() {
+        assert_eq!("voiceover".parse::<Ducking>().unwrap(), Ducking::VOICEOVER);
+        assert_eq!(" VLOG ".parse::<Ducking>().unwrap(), Ducking::VLOG);
+        assert!("podcast".parse::<Ducking>().is_err());
+    }
+
+    #[test]
+    fn ducking_filter_converts_threshold_to_linear() {
+        let duck = Ducking {
+            threshold_db: -20.0,
+            ..Ducking::VLOG
+        };
+        assert_eq!(
+            duck.filter(),
+            "sidechaincompress=threshold=0.1:ratio=4:attack=50:release=800"
+        );
+    }
+
+    #[test]
+    fn dither_parses() {
+        assert_eq!("bayer".parse::<Dither>().unwrap(), Dither::Bayer(2));
+        assert_eq!("Bayer:5".parse::<Dither>().unwrap(), Dither::Bayer(5));
+        assert_eq!("floyd".parse::<Dither>().unwrap(), Dither::FloydSteinberg);
+        assert_eq!("sierra".parse::<Dither>().unwrap(), Dither::Sierra2_4a);
+        assert_eq!("none".parse::<Dither>().unwrap(), Dither::None);
+        assert!("bayer:6".parse::<Dither>().is_err());
+        assert!("noise".parse::<Dither>().is_err());
+        assert_eq!(
+            Dither::Bayer(3).paletteuse_args(),
+            "dither=bayer:bayer_scale=3"
+        );
+    }
+
+    #[test]
+    fn gif_repeats_maps_play_counts() {
+        assert_eq!(gif_repeats(0), 0);
+        assert_eq!(gif_repeats(1), -1);
+        assert_eq!(gif_repeats(3), 2);
+    }
+
+    #[test]
+    fn make_gif_checks_format_and_fps() {
+        let input = Path::new("does-not-exist.mp4");
+        let options = GifOptions::default();
+        let err = make_gif(input, Path::new("out.mp4"), &options, |_| {}).unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported animation format"),
+            "{}",
+            err
+        );
+
+        let options = GifOptions {
+            fps: 0.0,
+            ..GifOptions::default()
+        };
+        let err = make_gif(input, Path::new("out.gif"), &options, |_| {}).unwrap_err();
+        assert!(err.to_string().contains("fps"), "{}", err);
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("25").unwrap(), 25_000_000);
+        assert_eq!(parse_size("800k").unwrap(), 800_000);
+        assert_eq!(parse_size("1.5 GB").unwrap(), 1_500_000_000);
+        assert_eq!(parse_size("100MiB").unwrap(), 100 * 1024 * 1024);
+        assert!(parse_size("0MB").is_err());
+        assert!(parse_size("10 TB").is_err());
+        assert!(parse_size("big").is_err());
+    }
 }
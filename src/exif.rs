@@ -0,0 +1,288 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// EXIF lives in the first APP1 segment, so the start of the file is enough.
+const READ_LIMIT: u64 = 256 * 1024;
+
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// The capture time of a JPEG or TIFF photo as the raw EXIF string
+/// (`YYYY:MM:DD HH:MM:SS`), which sorts chronologically as-is. Falls back to
+/// the IFD0 `DateTime` when `DateTimeOriginal` is missing.
+pub fn capture_time(path: &Path) -> Result<Option<String>> {
+    let mut data = Vec::new();
+    File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(READ_LIMIT)
+        .read_to_end(&mut data)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let tiff = if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        &data[..]
+    } else {
+        match jpeg_exif(&data) {
+            Some(tiff) => tiff,
+            None => return Ok(None),
+        }
+    };
+    Ok(Tiff::new(tiff).and_then(|t| t.capture_time()))
+}
+
+/// Finds the TIFF block inside a JPEG's `Exif` APP1 segment.
+fn jpeg_exif(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        // Start of scan: image data follows, no more metadata.
+        if marker == 0xDA {
+            return None;
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, pos: usize) -> Option<u16> {
+        let bytes = [*self.data.get(pos)?, *self.data.get(pos + 1)?];
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, pos: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Position of the 12-byte IFD entry for `tag` in the IFD at `ifd`.
+    fn find(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let count = self.u16_at(ifd)? as usize;
+        (0..count)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|entry| self.u16_at(*entry) == Some(tag))
+    }
+
+    /// Reads an ASCII entry; values over four bytes are stored at an offset.
+    fn ascii(&self, entry: usize) -> Option<String> {
+        let count = self.u32_at(entry + 4)? as usize;
+        let start = if count > 4 {
+            self.u32_at(entry + 8)? as usize
+        } else {
+            entry + 8
+        };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .trim()
+            .to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn capture_time(&self) -> Option<String> {
+        let ifd0 = self.u32_at(4)? as usize;
+        let original = self
+            .find(ifd0, TAG_EXIF_IFD)
+            .and_then(|entry| self.u32_at(entry + 8))
+            .and_then(|exif| self.find(exif as usize, TAG_DATE_TIME_ORIGINAL))
+            .and_then(|entry| self.ascii(entry));
+        original.or_else(|| {
+            self.find(ifd0, TAG_DATE_TIME)
+                .and_then(|entry| self.ascii(entry))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "2024:05:01 06:30:00";
+    const MODIFIED: &str = "2024:06:12 18:00:00";
+
+    /// A TIFF block with an optional IFD0 `DateTime` and an optional
+    /// `DateTimeOriginal` reached through the ExifIFD pointer.
+    fn tiff(little_endian: bool, original: Option<&str>, modified: Option<&str>) -> Vec<u8> {
+        let u16b = |v: u16| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+        let u32b = |v: u32| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+        let ascii = |tag: u16, offset: u32| {
+            let mut e = Vec::new();
+            e.extend(u16b(tag));
+            e.extend(u16b(2));
+            e.extend(u32b(20));
+            e.extend(u32b(offset));
+            e
+        };
+
+        let ifd0_count = original.is_some() as u32 + modified.is_some() as u32;
+        let ifd0_len = 2 + 12 * ifd0_count + 4;
+        let exif_ifd = 8 + ifd0_len;
+        let exif_len = if original.is_some() { 2 + 12 + 4 } else { 0 };
+        let modified_at = exif_ifd + exif_len;
+        let original_at = modified_at + 20;
+
+        let mut out = Vec::new();
+        out.extend(if little_endian { b"II" } else { b"MM" });
+        out.extend(u16b(42));
+        out.extend(u32b(8));
+
+        out.extend(u16b(ifd0_count as u16));
+        if modified.is_some() {
+            out.extend(ascii(TAG_DATE_TIME, modified_at));
+        }
+        if original.is_some() {
+            out.extend(u16b(TAG_EXIF_IFD));
+            out.extend(u16b(4));
+            out.extend(u32b(1));
+            out.extend(u32b(exif_ifd));
+        }
+        out.extend(u32b(0));
+
+        if original.is_some() {
+            out.extend(u16b(1));
+            out.extend(ascii(TAG_DATE_TIME_ORIGINAL, original_at));
+            out.extend(u32b(0));
+        }
+        for value in [modified.unwrap_or(""), original.unwrap_or("")] {
+            let mut text = value.as_bytes().to_vec();
+            text.resize(20, 0);
+            out.extend(text);
+        }
+        out
+    }
+
+    fn capture(data: &[u8]) -> Option<String> {
+        Tiff::new(data).and_then(|t| t.capture_time())
+    }
+
+    #[test]
+    fn reads_little_and_big_endian() {
+        for little_endian in [true, false] {
+            let data = tiff(little_endian, Some(ORIGINAL), None);
+            assert_eq!(capture(&data).as_deref(), Some(ORIGINAL));
+        }
+    }
+
+    #[test]
+    fn follows_exif_ifd_before_ifd0_date() {
+        let data = tiff(true, Some(ORIGINAL), Some(MODIFIED));
+        assert_eq!(capture(&data).as_deref(), Some(ORIGINAL));
+    }
+
+    #[test]
+    fn falls_back_to_date_time() {
+        let data = tiff(false, None, Some(MODIFIED));
+        assert_eq!(capture(&data).as_deref(), Some(MODIFIED));
+        assert_eq!(capture(&tiff(true, None, None)), None);
+    }
+
+    #[test]
+    fn finds_tiff_inside_jpeg_app1() {
+        let block = tiff(true, Some(ORIGINAL), None);
+        let mut jpeg = vec![0xFF, 0xD8];
+        // An unrelated APP0 segment comes first in most files.
+        jpeg.extend([0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00]);
+        jpeg.extend([0xFF, 0xE1]);
+        jpeg.extend(((block.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(&block);
+        jpeg.extend([0xFF, 0xDA, 0x00, 0x02]);
+
+        assert_eq!(jpeg_exif(&jpeg), Some(&block[..]));
+
+        let path = std::env::temp_dir().join(format!("framix_exif_{}.jpg", std::process::id()));
+        std::fs::write(&path, &jpeg).unwrap();
+        let time = capture_time(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(time.unwrap().as_deref(), Some(ORIGINAL));
+    }
+
+    #[test]
+    fn truncated_data_returns_none() {
+        let data = tiff(true, Some(ORIGINAL), Some(MODIFIED));
+        for len in 0..data.len() - 20 {
+            // Cutting into the original's text can still leave the
+            // modified time readable; it must never panic.
+            let _ = capture(&data[..len]);
+        }
+        assert_eq!(capture(&data[..10]), None);
+
+        let block = tiff(true, Some(ORIGINAL), None);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend(((block.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(&block[..block.len() / 2]);
+        assert_eq!(jpeg_exif(&jpeg), None);
+        assert_eq!(jpeg_exif(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x00]), None);
+    }
+
+    #[test]
+    fn out_of_bounds_offsets_return_none() {
+        let mut data = tiff(true, Some(ORIGINAL), None);
+        // Point the ExifIFD entry far past the end of the block.
+        let pointer = 8 + 2 + 8;
+        data[pointer..pointer + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(capture(&data), None);
+
+        let mut data = tiff(false, None, Some(MODIFIED));
+        // IFD0 offset past the end.
+        data[4..8].copy_from_slice(&0x7FFF_FFFFu32.to_be_bytes());
+        assert_eq!(capture(&data), None);
+
+        let mut data = tiff(true, None, Some(MODIFIED));
+        // A string count that runs off the end.
+        let count = 8 + 2 + 4;
+        data[count..count + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(capture(&data), None);
+    }
+}
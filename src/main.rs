@@ -1,5 +1,5 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -16,6 +16,39 @@ enum Commands {
         inputs: Vec<PathBuf>,
         #[arg(short, long)]
         output: PathBuf,
+        /// Canvas to normalize clips to: first, largest or WxH@fps
+        #[arg(long, default_value = "first")]
+        target: commands::CombineTarget,
+        /// auto (stream copy when inputs match), copy or reencode
+        #[arg(long, default_value = "auto")]
+        mode: commands::CombineMode,
+        /// Transition for every join (e.g. fade) or one per join
+        /// (e.g. fade,wipeleft:0.5,none)
+        #[arg(long)]
+        transition: Option<String>,
+        /// Default transition length in seconds
+        #[arg(long, default_value_t = 1.0)]
+        transition_duration: f64,
+    },
+    #[command(group(ArgGroup::new("mode").required(true).args(["every", "at", "chapters"])))]
+    Split {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output name template with a counter, e.g. part_%03d.mp4
+        #[arg(short, long)]
+        output: String,
+        /// Segment length (seconds or HH:MM:SS)
+        #[arg(long)]
+        every: Option<String>,
+        /// Comma-separated cut points, e.g. 1:30,4:00,10:15
+        #[arg(long, value_delimiter = ',')]
+        at: Vec<String>,
+        /// Cut at the chapter markers embedded in the input
+        #[arg(long)]
+        chapters: bool,
+        /// Re-encode so every segment starts exactly on its cut point
+        #[arg(long)]
+        accurate: bool,
     },
     Compress {
         #[arg(short, long)]
@@ -24,6 +57,34 @@ enum Commands {
         output: PathBuf,
         #[arg(long, default_value_t = 23)]
         crf: u8,
+        /// Hit a file size (e.g. 25MB) with a two-pass encode instead of CRF
+        #[arg(long)]
+        target_size: Option<String>,
+        /// Video codec: h264, h265, vp9 or av1
+        #[arg(long, default_value = "h264")]
+        codec: commands::VideoCodec,
+        /// Encoder speed preset (e.g. slow, veryfast; a number for AV1/VP9)
+        #[arg(long)]
+        preset: Option<String>,
+        #[arg(long)]
+        tune: Option<String>,
+        #[arg(long)]
+        pix_fmt: Option<String>,
+        /// aac, opus, mp3, copy or none (default: suits the container)
+        #[arg(long)]
+        audio_codec: Option<commands::AudioCodec>,
+        /// Audio bitrate in kb/s
+        #[arg(long, default_value_t = 128)]
+        audio_bitrate: u32,
+        /// Downscale to at most this width (never upscales)
+        #[arg(long)]
+        max_width: Option<u32>,
+        /// Downscale to at most this height (never upscales)
+        #[arg(long)]
+        max_height: Option<u32>,
+        /// Reduce the frame rate to at most this value
+        #[arg(long)]
+        max_fps: Option<f64>,
     },
     AddMusic {
         #[arg(short, long)]
@@ -32,24 +93,350 @@ enum Commands {
         audio: PathBuf,
         #[arg(short, long)]
         output: PathBuf,
-        #[arg(long, default_value = "1.0")]
+        /// Volume of the original audio: a factor (0.2) or a gain (-6dB)
+        #[arg(long, default_value = "1.0", allow_hyphen_values = true, value_parser = commands::parse_volume)]
         reduce_original: String,
+        /// Repeat the music until the video ends
+        #[arg(long = "loop")]
+        loop_music: bool,
+        /// Skip this far into the music track
+        #[arg(long, default_value = "0", value_parser = commands::parse_timestamp)]
+        music_start: f64,
+        /// Start the music this far into the video
+        #[arg(long, default_value = "0", value_parser = commands::parse_timestamp)]
+        delay: f64,
+        /// Music fade-in length in seconds
+        #[arg(long, default_value_t = 0.0)]
+        fade_in: f64,
+        /// Music fade-out length in seconds
+        #[arg(long, default_value_t = 0.0)]
+        fade_out: f64,
+        /// Duck the music under speech: voiceover or vlog preset
+        #[arg(long)]
+        duck: Option<commands::Ducking>,
+        /// Override the ducking threshold (dB)
+        #[arg(long, requires = "duck", allow_negative_numbers = true)]
+        duck_threshold: Option<f64>,
+        /// Override the ducking ratio
+        #[arg(long, requires = "duck")]
+        duck_ratio: Option<f64>,
+        /// Override the ducking attack (ms)
+        #[arg(long, requires = "duck")]
+        duck_attack: Option<f64>,
+        /// Override the ducking release (ms)
+        #[arg(long, requires = "duck")]
+        duck_release: Option<f64>,
     },
     Timelapse {
+        /// A video, or a folder of photos to turn into one
         #[arg(short, long)]
         input: PathBuf,
         #[arg(short, long)]
         output: PathBuf,
+        /// Speed factor (video input; required unless --ramp is given)
+        #[arg(short, long, conflicts_with = "ramp")]
+        speed: Option<f64>,
+        /// setpts (retime every frame, default), sample (keep every Nth
+        /// frame, fast) or blend (average each group of N frames for motion blur)
+        #[arg(long)]
+        method: Option<commands::TimelapseMethod>,
+        /// drop (default), preserve-pitch (atempo) or shift-pitch
+        #[arg(long)]
+        audio: Option<commands::TimelapseAudio>,
+        /// Motion-interpolate new frames for slow motion (speed below 1.0)
+        #[arg(long)]
+        interpolate: bool,
+        /// Output frame rate (default: same as the source)
+        #[arg(long)]
+        fps: Option<f64>,
+        /// Per-range speeds, e.g. 10-60@8 or 0:10-1:00@8,1:00-@0.5;
+        /// uncovered time plays at normal speed
+        #[arg(long)]
+        ramp: Option<String>,
+        /// Photo order for folder input: natural (by name, default) or exif
+        /// (capture time)
+        #[arg(long)]
+        sort: Option<commands::ImageSort>,
+        /// Output width for folder input (height keeps the aspect ratio)
+        #[arg(long)]
+        width: Option<u32>,
+        /// Smooth exposure flicker between photos (folder input)
+        #[arg(long)]
+        deflicker: bool,
+    },
+    Trim {
         #[arg(short, long)]
-        speed: f64,
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(short, long)]
+        start: Option<String>,
+        #[arg(short, long, conflicts_with = "duration")]
+        end: Option<String>,
+        #[arg(short, long)]
+        duration: Option<String>,
+        /// Re-encode for a frame-accurate cut instead of stream copying
+        #[arg(long)]
+        accurate: bool,
+    },
+    Gif {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output file: .gif, .webp or .apng
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(short, long, default_value = "0", value_parser = commands::parse_timestamp)]
+        start: f64,
+        #[arg(short, long, conflicts_with = "duration", value_parser = commands::parse_timestamp)]
+        end: Option<f64>,
+        #[arg(short, long, value_parser = commands::parse_timestamp)]
+        duration: Option<f64>,
+        #[arg(long, default_value_t = 12.0)]
+        fps: f64,
+        /// Output width in pixels (0 keeps the source width)
+        #[arg(short, long, default_value_t = 480)]
+        width: u32,
+        /// Number of plays; 0 loops forever
+        #[arg(long = "loop", default_value_t = 0)]
+        loop_count: u32,
+        /// GIF dithering: bayer[:0-5], floyd_steinberg, sierra2, sierra2_4a or none
+        #[arg(long, default_value = "sierra2_4a")]
+        dither: commands::Dither,
+    },
+    #[command(group(ArgGroup::new("mode").required(true).args(["at", "best", "sheet"])))]
+    Thumbnails {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output image, or a template with a counter such as thumb_%02d.jpg
+        #[arg(short, long)]
+        output: String,
+        /// Comma-separated timestamps to grab, e.g. 0:05,1:30,2:00
+        #[arg(long, value_delimiter = ',')]
+        at: Vec<String>,
+        /// Pick this many representative frames with the thumbnail filter
+        #[arg(long)]
+        best: Option<u32>,
+        /// Build a contact sheet grid, e.g. 4x3
+        #[arg(long)]
+        sheet: Option<String>,
+        /// Stamp each contact sheet frame with its timestamp
+        #[arg(long, requires = "sheet")]
+        labels: bool,
+        /// Width of each frame in pixels (0 keeps the source width)
+        #[arg(short, long, default_value_t = 320)]
+        width: u32,
+    },
+    #[command(group(ArgGroup::new("selection").args(["every", "fps"])))]
+    Frames {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output name template with a counter, e.g. frame_%05d.png
+        #[arg(short, long)]
+        output: String,
+        #[arg(short, long, default_value = "0", value_parser = commands::parse_timestamp)]
+        start: f64,
+        #[arg(short, long, conflicts_with = "duration", value_parser = commands::parse_timestamp)]
+        end: Option<f64>,
+        #[arg(short, long, value_parser = commands::parse_timestamp)]
+        duration: Option<f64>,
+        /// Keep every Nth frame (default: every frame)
+        #[arg(long)]
+        every: Option<u32>,
+        /// Sample at this frame rate instead
+        #[arg(long)]
+        fps: Option<f64>,
+        /// JPEG quality, 1-100
+        #[arg(short, long, default_value_t = 90)]
+        quality: u8,
+    },
+    Slideshow {
+        /// Images, or folders of images (sorted by name)
+        #[arg(short, long, required = true, num_args = 1..)]
+        inputs: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Seconds per slide: one value, or one per image (e.g. 3,5,4)
+        #[arg(short, long, value_delimiter = ',', default_value = "4")]
+        duration: Vec<f64>,
+        /// Transition for every join (e.g. fade) or one per join; none for cuts
+        #[arg(long, default_value = "fade")]
+        transition: String,
+        /// Default transition length in seconds
+        #[arg(long, default_value_t = 1.0)]
+        transition_duration: f64,
+        #[arg(long, default_value_t = 1920)]
+        width: u32,
+        #[arg(long, default_value_t = 1080)]
+        height: u32,
+        #[arg(long, default_value_t = 30.0)]
+        fps: f64,
+        /// contain (letterbox) or cover (crop to fill)
+        #[arg(long, default_value = "contain")]
+        fit: commands::SlideFit,
+        /// Slow pan/zoom on every slide
+        #[arg(long)]
+        ken_burns: bool,
+        /// Soundtrack, cut to the slideshow length
+        #[arg(short, long)]
+        music: Option<PathBuf>,
+        /// Repeat the music if it's shorter than the slideshow
+        #[arg(long = "loop", requires = "music")]
+        loop_music: bool,
+        /// Skip this far into the music track
+        #[arg(long, default_value = "0", value_parser = commands::parse_timestamp)]
+        music_start: f64,
+        /// Music fade-in length in seconds
+        #[arg(long, default_value_t = 0.0)]
+        fade_in: f64,
+        /// Music fade-out length in seconds
+        #[arg(long, default_value_t = 2.0)]
+        fade_out: f64,
+    },
+    Subtitles {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Subtitle files (.srt, .ass, .ssa, .vtt); burning takes one
+        #[arg(short, long, required = true, num_args = 1..)]
+        subtitles: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Render the subtitles into the picture instead of adding a track
+        #[arg(long)]
+        burn: bool,
+        /// ISO 639 language per subtitle file, e.g. eng,deu
+        #[arg(short, long, value_delimiter = ',', conflicts_with = "burn")]
+        language: Vec<String>,
+        #[arg(long, requires = "burn")]
+        font: Option<String>,
+        #[arg(long, requires = "burn")]
+        font_size: Option<u32>,
+        /// Text colour: #RRGGBB or a name like white or yellow
+        #[arg(long, requires = "burn")]
+        color: Option<String>,
+        /// Outline colour: #RRGGBB or a name
+        #[arg(long, requires = "burn")]
+        outline_color: Option<String>,
+        /// Outline width in pixels
+        #[arg(long, requires = "burn")]
+        outline: Option<f64>,
+    },
+    Normalize {
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// streaming (-14 LUFS), podcast (-16), broadcast (-23) or a LUFS value
+        #[arg(
+            short,
+            long,
+            default_value = "streaming",
+            allow_negative_numbers = true
+        )]
+        target: commands::LoudnessTarget,
+        /// Override the true-peak ceiling (dBTP)
+        #[arg(long, allow_negative_numbers = true)]
+        true_peak: Option<f64>,
+        /// Override the loudness range target (LU)
+        #[arg(long)]
+        lra: Option<f64>,
+    },
+    Audio {
+        #[command(subcommand)]
+        action: AudioAction,
+    },
+    Subs {
+        #[command(subcommand)]
+        action: SubsAction,
     },
     Info {
         #[arg(short, long)]
         input: PathBuf,
+        /// Print the probe result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AudioAction {
+    /// Save one audio stream as mp3, wav, aac, opus or flac
+    Extract {
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// mp3, wav, aac, opus or flac (default: from the output extension)
+        #[arg(short, long)]
+        format: Option<commands::AudioFormat>,
+        /// Audio stream to use, counting from 0
+        #[arg(short, long, default_value_t = 0)]
+        stream: usize,
+        /// Bitrate in kb/s for lossy formats (forces a re-encode)
+        #[arg(short, long)]
+        bitrate: Option<u32>,
+    },
+    /// Remove all audio, keeping the video untouched
+    Strip {
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Replace the audio with another track, without mixing
+    Replace {
+        #[arg(short, long)]
+        video: PathBuf,
+        #[arg(short, long)]
+        audio: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Audio stream of the new track to use, counting from 0
+        #[arg(short, long, default_value_t = 0)]
+        stream: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum SubsAction {
+    /// Convert between SRT, WebVTT and ASS and/or adjust timings
+    Convert {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Output file; the format follows the extension (.srt, .vtt, .ass)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Match a trim: drop cues before this point and start from zero
+        #[arg(long, default_value = "0", value_parser = commands::parse_timestamp)]
+        trim_start: f64,
+        /// Match a trim: drop cues after this point of the source
+        #[arg(long, value_parser = commands::parse_timestamp)]
+        trim_end: Option<f64>,
+        /// Match a timelapse: divide all times by this speed factor
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Multiply all times by this factor (e.g. 25/23.976 fixes)
+        #[arg(long, default_value_t = 1.0)]
+        stretch: f64,
+        /// Seconds to move every cue by; negative moves them earlier
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        shift: f64,
+    },
+    /// Save an embedded subtitle stream as SRT, WebVTT or ASS
+    Extract {
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Subtitle stream to use, counting from 0
+        #[arg(short, long, default_value_t = 0)]
+        stream: usize,
     },
 }
 
 mod commands;
+mod exif;
+mod probe;
+mod subtitle;
 mod tui;
 
 fn main() -> Result<()> {
@@ -66,29 +453,416 @@ fn main() -> Result<()> {
         };
 
         match &cli.command {
-            Commands::Combine { inputs, output } => {
-                commands::combine_videos(inputs, output, print_progress)?;
+            Commands::Combine {
+                inputs,
+                output,
+                target,
+                mode,
+                transition,
+                transition_duration,
+            } => {
+                let transitions = match transition {
+                    Some(spec) => commands::parse_transitions(spec, *transition_duration)?,
+                    None => Vec::new(),
+                };
+                let options = commands::CombineOptions {
+                    target: *target,
+                    mode: *mode,
+                    transitions,
+                };
+                commands::combine_videos(inputs, output, &options, print_progress)?;
             }
-            Commands::Compress { input, output, crf } => {
-                commands::compress_video(input, output, *crf, print_progress)?;
+            Commands::Split {
+                input,
+                output,
+                every,
+                at,
+                chapters,
+                accurate,
+            } => {
+                let mode = if *chapters {
+                    commands::SplitMode::Chapters
+                } else if let Some(every) = every {
+                    commands::SplitMode::Every(commands::parse_timestamp(every)?)
+                } else {
+                    let times = at
+                        .iter()
+                        .map(|t| commands::parse_timestamp(t))
+                        .collect::<Result<Vec<_>>>()?;
+                    commands::SplitMode::At(times)
+                };
+                commands::split_video(input, output, &mode, *accurate, print_progress)?;
+            }
+            Commands::Compress {
+                input,
+                output,
+                crf,
+                target_size,
+                codec,
+                preset,
+                tune,
+                pix_fmt,
+                audio_codec,
+                audio_bitrate,
+                max_width,
+                max_height,
+                max_fps,
+            } => {
+                let options = commands::CompressOptions {
+                    crf: *crf,
+                    target_size: target_size
+                        .as_deref()
+                        .map(commands::parse_size)
+                        .transpose()?,
+                    codec: *codec,
+                    preset: preset.clone(),
+                    tune: tune.clone(),
+                    pix_fmt: pix_fmt.clone(),
+                    audio_codec: *audio_codec,
+                    audio_bitrate: *audio_bitrate,
+                    max_width: *max_width,
+                    max_height: *max_height,
+                    max_fps: *max_fps,
+                };
+                commands::compress_video(input, output, &options, print_progress)?;
             }
             Commands::AddMusic {
                 video,
                 audio,
                 output,
                 reduce_original,
+                loop_music,
+                music_start,
+                delay,
+                fade_in,
+                fade_out,
+                duck,
+                duck_threshold,
+                duck_ratio,
+                duck_attack,
+                duck_release,
             } => {
-                commands::add_music(video, audio, output, reduce_original, print_progress)?;
+                let duck = duck.map(|preset| commands::Ducking {
+                    threshold_db: duck_threshold.unwrap_or(preset.threshold_db),
+                    ratio: duck_ratio.unwrap_or(preset.ratio),
+                    attack: duck_attack.unwrap_or(preset.attack),
+                    release: duck_release.unwrap_or(preset.release),
+                });
+                let options = commands::MusicOptions {
+                    reduce_original: reduce_original.clone(),
+                    loop_music: *loop_music,
+                    music_start: *music_start,
+                    delay: *delay,
+                    fade_in: *fade_in,
+                    fade_out: *fade_out,
+                    duck,
+                };
+                commands::add_music(video, audio, output, &options, print_progress)?;
             }
             Commands::Timelapse {
                 input,
                 output,
                 speed,
+                method,
+                audio,
+                interpolate,
+                fps,
+                ramp,
+                sort,
+                width,
+                deflicker,
+            } => {
+                if input.is_dir() {
+                    let video_only: Vec<&str> = [
+                        ("--speed", speed.is_some()),
+                        ("--method", method.is_some()),
+                        ("--audio", audio.is_some()),
+                        ("--interpolate", *interpolate),
+                        ("--ramp", ramp.is_some()),
+                    ]
+                    .into_iter()
+                    .filter_map(|(flag, given)| given.then_some(flag))
+                    .collect();
+                    if !video_only.is_empty() {
+                        anyhow::bail!(
+                            "{} can only be used with video input, not a folder of photos",
+                            video_only.join(", ")
+                        );
+                    }
+                    let options = commands::ImageSequenceOptions {
+                        sort: sort.unwrap_or_default(),
+                        fps: fps.unwrap_or(30.0),
+                        width: *width,
+                        deflicker: *deflicker,
+                    };
+                    commands::timelapse_from_images(input, output, &options, print_progress)?;
+                } else {
+                    let folder_only: Vec<&str> = [
+                        ("--sort", sort.is_some()),
+                        ("--width", width.is_some()),
+                        ("--deflicker", *deflicker),
+                    ]
+                    .into_iter()
+                    .filter_map(|(flag, given)| given.then_some(flag))
+                    .collect();
+                    if !folder_only.is_empty() {
+                        anyhow::bail!(
+                            "{} can only be used with a folder of photos, not video input",
+                            folder_only.join(", ")
+                        );
+                    }
+                    if speed.is_none() && ramp.is_none() {
+                        anyhow::bail!("--speed or --ramp is required when the input is a video");
+                    }
+                    let options = commands::TimelapseOptions {
+                        speed: speed.unwrap_or(1.0),
+                        method: method.unwrap_or_default(),
+                        audio: audio.unwrap_or_default(),
+                        interpolate: *interpolate,
+                        fps: *fps,
+                        ramp: match ramp {
+                            Some(spec) => commands::parse_speed_ramp(spec)?,
+                            None => Vec::new(),
+                        },
+                    };
+                    commands::timelapse(input, output, &options, print_progress)?;
+                }
+            }
+            Commands::Trim {
+                input,
+                output,
+                start,
+                end,
+                duration,
+                accurate,
+            } => {
+                commands::trim_video(
+                    input,
+                    output,
+                    start.as_deref(),
+                    end.as_deref(),
+                    duration.as_deref(),
+                    *accurate,
+                    print_progress,
+                )?;
+            }
+            Commands::Gif {
+                input,
+                output,
+                start,
+                end,
+                duration,
+                fps,
+                width,
+                loop_count,
+                dither,
+            } => {
+                let options = commands::GifOptions {
+                    start: *start,
+                    end: *end,
+                    duration: *duration,
+                    fps: *fps,
+                    width: (*width > 0).then_some(*width),
+                    loop_count: *loop_count,
+                    dither: *dither,
+                };
+                commands::make_gif(input, output, &options, print_progress)?;
+            }
+            Commands::Thumbnails {
+                input,
+                output,
+                at,
+                best,
+                sheet,
+                labels,
+                width,
+            } => {
+                let mode = if let Some(grid) = sheet {
+                    let (columns, rows) = commands::parse_grid(grid)?;
+                    commands::ThumbnailMode::Sheet {
+                        columns,
+                        rows,
+                        labels: *labels,
+                    }
+                } else if let Some(count) = best {
+                    commands::ThumbnailMode::Representative(*count)
+                } else {
+                    commands::ThumbnailMode::At(
+                        at.iter()
+                            .map(|t| commands::parse_timestamp(t))
+                            .collect::<anyhow::Result<_>>()?,
+                    )
+                };
+                let width = (*width > 0).then_some(*width);
+                commands::thumbnails(input, output, &mode, width, print_progress)?;
+            }
+            Commands::Frames {
+                input,
+                output,
+                start,
+                end,
+                duration,
+                every,
+                fps,
+                quality,
+            } => {
+                let selection = match (every, fps) {
+                    (Some(n), _) => commands::FrameSelection::EveryNth(*n),
+                    (None, Some(rate)) => commands::FrameSelection::Fps(*rate),
+                    (None, None) => commands::FrameSelection::All,
+                };
+                let options = commands::FramesOptions {
+                    start: *start,
+                    end: *end,
+                    duration: *duration,
+                    selection,
+                    jpeg_quality: *quality,
+                };
+                commands::extract_frames(input, output, &options, print_progress)?;
+            }
+            Commands::Slideshow {
+                inputs,
+                output,
+                duration,
+                transition,
+                transition_duration,
+                width,
+                height,
+                fps,
+                fit,
+                ken_burns,
+                music,
+                loop_music,
+                music_start,
+                fade_in,
+                fade_out,
+            } => {
+                let images = commands::collect_images(inputs)?;
+                let options = commands::SlideshowOptions {
+                    width: *width,
+                    height: *height,
+                    fps: *fps,
+                    durations: duration.clone(),
+                    transitions: commands::parse_transitions(transition, *transition_duration)?,
+                    fit: *fit,
+                    ken_burns: *ken_burns,
+                    music: music.clone(),
+                    music_options: commands::MusicOptions {
+                        loop_music: *loop_music,
+                        music_start: *music_start,
+                        fade_in: *fade_in,
+                        fade_out: *fade_out,
+                        ..Default::default()
+                    },
+                };
+                commands::slideshow(&images, output, &options, print_progress)?;
+            }
+            Commands::Subtitles {
+                input,
+                subtitles,
+                output,
+                burn,
+                language,
+                font,
+                font_size,
+                color,
+                outline_color,
+                outline,
+            } => {
+                if *burn {
+                    let [subtitle] = subtitles.as_slice() else {
+                        anyhow::bail!("Burning in takes exactly one subtitle file");
+                    };
+                    let style = commands::SubtitleStyle {
+                        font: font.clone(),
+                        font_size: *font_size,
+                        color: color.clone(),
+                        outline_color: outline_color.clone(),
+                        outline: *outline,
+                    };
+                    commands::burn_subtitles(input, subtitle, output, &style, print_progress)?;
+                } else {
+                    commands::mux_subtitles(input, subtitles, output, language, print_progress)?;
+                }
+            }
+            Commands::Normalize {
+                input,
+                output,
+                target,
+                true_peak,
+                lra,
             } => {
-                commands::timelapse(input, output, *speed, print_progress)?;
+                let target = commands::LoudnessTarget {
+                    true_peak: true_peak.unwrap_or(target.true_peak),
+                    lra: lra.unwrap_or(target.lra),
+                    ..*target
+                };
+                let (before, after) =
+                    commands::normalize_loudness(input, output, &target, print_progress)?;
+                println!("Loudness summary for {}", input.display());
+                println!("  Before: {}", before);
+                println!("  After:  {}", after);
+                println!(
+                    "  Target: {:.1} LUFS, true peak {:.1} dBTP, LRA {:.1} LU",
+                    target.integrated, target.true_peak, target.lra
+                );
             }
-            Commands::Info { input } => {
-                commands::get_info(input, print_progress)?;
+            Commands::Audio { action } => match action {
+                AudioAction::Extract {
+                    input,
+                    output,
+                    format,
+                    stream,
+                    bitrate,
+                } => {
+                    let options = commands::ExtractOptions {
+                        stream: *stream,
+                        format: *format,
+                        bitrate: *bitrate,
+                    };
+                    commands::extract_audio(input, output, &options, print_progress)?;
+                }
+                AudioAction::Strip { input, output } => {
+                    commands::strip_audio(input, output, print_progress)?;
+                }
+                AudioAction::Replace {
+                    video,
+                    audio,
+                    output,
+                    stream,
+                } => {
+                    commands::replace_audio(video, audio, output, *stream, print_progress)?;
+                }
+            },
+            Commands::Subs { action } => match action {
+                SubsAction::Convert {
+                    input,
+                    output,
+                    trim_start,
+                    trim_end,
+                    speed,
+                    stretch,
+                    shift,
+                } => {
+                    let timing = subtitle::Retime {
+                        trim_start: *trim_start,
+                        trim_end: *trim_end,
+                        speed: *speed,
+                        stretch: *stretch,
+                        shift: *shift,
+                    };
+                    commands::convert_subtitles(input, output, &timing, print_progress)?;
+                }
+                SubsAction::Extract {
+                    input,
+                    output,
+                    stream,
+                } => {
+                    commands::extract_subtitles(input, output, *stream, print_progress)?;
+                }
+            },
+            Commands::Info { input, json } => {
+                commands::get_info(input, *json, print_progress)?;
             }
         }
     } else {
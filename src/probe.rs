@@ -0,0 +1,480 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub profile: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pix_fmt: Option<String>,
+    pub fps: Option<f64>,
+    pub time_base: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub bit_rate: Option<u64>,
+    pub duration: Option<f64>,
+    /// Cover art embedded in audio files shows up as a one-frame video stream.
+    pub attached_pic: bool,
+    /// Display rotation in degrees (phone clips shot upright are often 90 or
+    /// -90); `width`/`height` are the coded size before rotating.
+    pub rotation: i32,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl StreamInfo {
+    /// Width and height as played back, i.e. after ffmpeg applies the
+    /// rotation metadata (which it does before any filter runs).
+    pub fn display_size(&self) -> Option<(u32, u32)> {
+        let (w, h) = (self.width?, self.height?);
+        if self.rotation.rem_euclid(180) == 90 {
+            Some((h, w))
+        } else {
+            Some((w, h))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterInfo {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+/// Everything Framix needs to know about a media file, built from
+/// `ffprobe -print_format json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaInfo {
+    pub path: String,
+    pub format: Option<String>,
+    pub format_long_name: Option<String>,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub size: Option<u64>,
+    pub tags: BTreeMap<String, String>,
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<ChapterInfo>,
+}
+
+impl MediaInfo {
+    /// The first video stream, ignoring cover art attached to audio files.
+    pub fn video(&self) -> Option<&StreamInfo> {
+        self.streams
+            .iter()
+            .find(|s| s.kind == StreamKind::Video && !s.attached_pic)
+    }
+
+    pub fn audio(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.kind == StreamKind::Audio)
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio().is_some()
+    }
+
+    /// Container duration, falling back to the longest stream.
+    pub fn duration_secs(&self) -> Result<f64> {
+        self.duration
+            .or_else(|| {
+                self.streams
+                    .iter()
+                    .filter_map(|s| s.duration)
+                    .reduce(f64::max)
+            })
+            .ok_or_else(|| anyhow!("Could not determine duration of {}", self.path))
+    }
+}
+
+impl fmt::Display for MediaInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "File: {}", self.path)?;
+        if let Some(format) = self.format_long_name.as_ref().or(self.format.as_ref()) {
+            writeln!(f, "Format: {}", format)?;
+        }
+        if let Some(d) = self.duration {
+            writeln!(f, "Duration: {}", format_duration(d))?;
+        }
+        if let Some(b) = self.bit_rate {
+            writeln!(f, "Bitrate: {} kb/s", b / 1000)?;
+        }
+        if let Some(size) = self.size {
+            writeln!(f, "Size: {:.2} MB", size as f64 / 1_000_000.0)?;
+        }
+        for (key, value) in &self.tags {
+            writeln!(f, "  {}: {}", key, value)?;
+        }
+
+        for s in &self.streams {
+            let codec = s.codec.as_deref().unwrap_or("unknown");
+            match s.kind {
+                StreamKind::Video => {
+                    write!(f, "Stream #{}: Video: {}", s.index, codec)?;
+                    if let (Some(w), Some(h)) = (s.width, s.height) {
+                        write!(f, ", {}x{}", w, h)?;
+                    }
+                    if let Some(fps) = s.fps {
+                        write!(f, ", {:.2} fps", fps)?;
+                    }
+                    if let Some(pix_fmt) = &s.pix_fmt {
+                        write!(f, ", {}", pix_fmt)?;
+                    }
+                }
+                StreamKind::Audio => {
+                    write!(f, "Stream #{}: Audio: {}", s.index, codec)?;
+                    if let Some(rate) = s.sample_rate {
+                        write!(f, ", {} Hz", rate)?;
+                    }
+                    if let Some(layout) = &s.channel_layout {
+                        write!(f, ", {}", layout)?;
+                    } else if let Some(channels) = s.channels {
+                        write!(f, ", {} channels", channels)?;
+                    }
+                }
+                _ => {
+                    write!(f, "Stream #{}: {:?}: {}", s.index, s.kind, codec)?;
+                }
+            }
+            if let Some(b) = s.bit_rate {
+                write!(f, ", {} kb/s", b / 1000)?;
+            }
+            if let Some(lang) = s.tags.get("language") {
+                write!(f, " ({})", lang)?;
+            }
+            writeln!(f)?;
+        }
+
+        for (i, c) in self.chapters.iter().enumerate() {
+            write!(
+                f,
+                "Chapter #{}: {} -> {}",
+                i + 1,
+                format_duration(c.start),
+                format_duration(c.end)
+            )?;
+            if let Some(title) = &c.title {
+                write!(f, " {}", title)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn format_duration(secs: f64) -> String {
+    let h = (secs / 3600.0).floor();
+    let m = ((secs - h * 3600.0) / 60.0).floor();
+    let s = secs - h * 3600.0 - m * 60.0;
+    format!("{:02}:{:02}:{:05.2}", h as u64, m as u64, s)
+}
+
+pub fn probe(path: &Path) -> Result<MediaInfo> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-print_format")
+        .arg("json")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg("-show_chapters")
+        .arg(path)
+        .output()
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "ffprobe failed on {}: {}",
+            path.display(),
+            stderr.trim()
+        ));
+    }
+
+    let raw: RawProbe =
+        serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe output")?;
+    Ok(raw.into_media_info(path))
+}
+
+// ffprobe reports most numbers as strings, so the raw model mirrors its JSON
+// and the conversion below does the parsing.
+
+#[derive(Deserialize)]
+struct RawProbe {
+    #[serde(default)]
+    streams: Vec<RawStream>,
+    #[serde(default)]
+    chapters: Vec<RawChapter>,
+    format: Option<RawFormat>,
+}
+
+#[derive(Deserialize)]
+struct RawStream {
+    index: u32,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    codec_long_name: Option<String>,
+    profile: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    pix_fmt: Option<String>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    time_base: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    bit_rate: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    disposition: BTreeMap<String, i64>,
+    #[serde(default)]
+    side_data_list: Vec<RawSideData>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+/// Newer ffprobe reports rotation in the display matrix side data rather
+/// than the `rotate` tag.
+#[derive(Deserialize)]
+struct RawSideData {
+    rotation: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct RawChapter {
+    start_time: Option<String>,
+    end_time: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct RawFormat {
+    format_name: Option<String>,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    size: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+impl RawProbe {
+    fn into_media_info(self, path: &Path) -> MediaInfo {
+        let format = self.format;
+        let parse_f64 = |v: &Option<String>| v.as_deref().and_then(|v| v.parse::<f64>().ok());
+        let parse_u64 = |v: &Option<String>| v.as_deref().and_then(|v| v.parse::<u64>().ok());
+
+        let streams = self
+            .streams
+            .into_iter()
+            .map(|s| StreamInfo {
+                index: s.index,
+                kind: match s.codec_type.as_deref() {
+                    Some("video") => StreamKind::Video,
+                    Some("audio") => StreamKind::Audio,
+                    Some("subtitle") => StreamKind::Subtitle,
+                    Some("data") => StreamKind::Data,
+                    Some("attachment") => StreamKind::Attachment,
+                    _ => StreamKind::Unknown,
+                },
+                fps: s
+                    .avg_frame_rate
+                    .as_deref()
+                    .and_then(parse_ratio)
+                    .or_else(|| s.r_frame_rate.as_deref().and_then(parse_ratio)),
+                sample_rate: s.sample_rate.as_deref().and_then(|r| r.parse().ok()),
+                bit_rate: parse_u64(&s.bit_rate),
+                duration: parse_f64(&s.duration),
+                attached_pic: s.disposition.get("attached_pic") == Some(&1),
+                rotation: s
+                    .side_data_list
+                    .iter()
+                    .find_map(|d| d.rotation)
+                    .or_else(|| s.tags.get("rotate").and_then(|r| r.parse().ok()))
+                    .map_or(0, |r| r.round() as i32),
+                codec: s.codec_name,
+                codec_long_name: s.codec_long_name,
+                profile: s.profile,
+                width: s.width,
+                height: s.height,
+                pix_fmt: s.pix_fmt,
+                time_base: s.time_base,
+                channels: s.channels,
+                channel_layout: s.channel_layout,
+                tags: s.tags,
+            })
+            .collect();
+
+        let chapters = self
+            .chapters
+            .into_iter()
+            .filter_map(|mut c| {
+                Some(ChapterInfo {
+                    start: parse_f64(&c.start_time)?,
+                    end: parse_f64(&c.end_time)?,
+                    title: c.tags.remove("title"),
+                })
+            })
+            .collect();
+
+        MediaInfo {
+            path: path.display().to_string(),
+            format: format.as_ref().and_then(|f| f.format_name.clone()),
+            format_long_name: format.as_ref().and_then(|f| f.format_long_name.clone()),
+            duration: format.as_ref().and_then(|f| parse_f64(&f.duration)),
+            bit_rate: format.as_ref().and_then(|f| parse_u64(&f.bit_rate)),
+            size: format.as_ref().and_then(|f| parse_u64(&f.size)),
+            tags: format.map(|f| f.tags).unwrap_or_default(),
+            streams,
+            chapters,
+        }
+    }
+}
+
+/// Parses an ffprobe rational such as `30000/1001`; `0/0` means unknown.
+fn parse_ratio(value: &str) -> Option<f64> {
+    let (num, den) = value.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    if num == 0.0 || den == 0.0 {
+        None
+    } else {
+        Some(num / den)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> MediaInfo {
+        serde_json::from_str::<RawProbe>(json)
+            .unwrap()
+            .into_media_info(Path::new("clip.mp4"))
+    }
+
+    #[test]
+    fn rotation_swaps_display_size() {
+        let info = parse(
+            r#"{"streams": [
+                {"index": 0, "codec_type": "video", "width": 1920, "height": 1080,
+                 "side_data_list": [{"side_data_type": "Display Matrix", "rotation": -90}]},
+                {"index": 1, "codec_type": "video", "width": 1920, "height": 1080,
+                 "tags": {"rotate": "180"}}
+            ]}"#,
+        );
+        assert_eq!(info.streams[0].rotation, -90);
+        assert_eq!(info.streams[0].display_size(), Some((1080, 1920)));
+        assert_eq!(info.streams[1].rotation, 180);
+        assert_eq!(info.streams[1].display_size(), Some((1920, 1080)));
+    }
+
+    #[test]
+    fn video_and_audio_streams() {
+        let info = parse(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "video", "codec_name": "h264",
+                     "width": 1920, "height": 1080, "pix_fmt": "yuv420p",
+                     "avg_frame_rate": "30000/1001", "r_frame_rate": "30000/1001",
+                     "bit_rate": "4500000", "duration": "12.012",
+                     "tags": {"language": "und"}},
+                    {"index": 1, "codec_type": "audio", "codec_name": "aac",
+                     "sample_rate": "48000", "channels": 2, "channel_layout": "stereo",
+                     "avg_frame_rate": "0/0", "r_frame_rate": "0/0", "duration": "12.05"}
+                ],
+                "chapters": [
+                    {"start_time": "0.000000", "end_time": "6.000000", "tags": {"title": "Intro"}}
+                ],
+                "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "12.050000",
+                           "bit_rate": "4650000", "size": "7004000", "tags": {"title": "Clip"}}
+            }"#,
+        );
+        let video = info.video().unwrap();
+        assert_eq!(video.codec.as_deref(), Some("h264"));
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert!((video.fps.unwrap() - 29.97).abs() < 0.001);
+        assert_eq!(video.bit_rate, Some(4_500_000));
+        let audio = info.audio().unwrap();
+        assert_eq!(audio.sample_rate, Some(48000));
+        assert_eq!(audio.fps, None);
+        assert_eq!(info.duration, Some(12.05));
+        assert_eq!(info.size, Some(7_004_000));
+        assert_eq!(info.tags.get("title").map(String::as_str), Some("Clip"));
+        assert_eq!(info.chapters[0].title.as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn audio_only_ignores_cover_art() {
+        let info = parse(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "audio", "codec_name": "mp3",
+                     "sample_rate": "44100", "channels": 2},
+                    {"index": 1, "codec_type": "video", "codec_name": "mjpeg",
+                     "width": 500, "height": 500, "r_frame_rate": "90000/1",
+                     "disposition": {"default": 0, "attached_pic": 1}}
+                ],
+                "format": {"format_name": "mp3", "duration": "180.5"}
+            }"#,
+        );
+        assert!(info.video().is_none());
+        assert!(info.has_audio());
+        assert_eq!(info.duration_secs().unwrap(), 180.5);
+    }
+
+    #[test]
+    fn missing_duration_falls_back_to_streams() {
+        let info = parse(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "video", "duration": "9.5"},
+                    {"index": 1, "codec_type": "audio", "duration": "10.25"}
+                ],
+                "format": {"format_name": "matroska,webm"}
+            }"#,
+        );
+        assert_eq!(info.duration, None);
+        assert_eq!(info.duration_secs().unwrap(), 10.25);
+
+        let info = parse(r#"{"streams": [{"index": 0, "codec_type": "data"}]}"#);
+        assert!(info.duration_secs().is_err());
+        assert_eq!(info.streams[0].kind, StreamKind::Data);
+    }
+
+    #[test]
+    fn frame_rates() {
+        assert_eq!(parse_ratio("0/0"), None);
+        assert_eq!(parse_ratio("25/1"), Some(25.0));
+        assert!((parse_ratio("30000/1001").unwrap() - 29.970_03).abs() < 1e-5);
+        assert_eq!(parse_ratio("30"), None);
+        assert_eq!(parse_ratio("x/1"), None);
+
+        // An unknown average rate falls back to r_frame_rate.
+        let info = parse(
+            r#"{"streams": [{"index": 0, "codec_type": "video",
+                "avg_frame_rate": "0/0", "r_frame_rate": "24000/1001"}]}"#,
+        );
+        assert!((info.streams[0].fps.unwrap() - 23.976).abs() < 0.001);
+    }
+}
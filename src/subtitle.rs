@@ -0,0 +1,469 @@
+use anyhow::{anyhow, Context, Result};
+use std::fmt::Write;
+use std::path::Path;
+
+/// One subtitle: when it shows, when it goes away and its text. Lines are
+/// separated by `\n`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+    /// Advanced SubStation Alpha. Only the dialogue text and timings are
+    /// read; styles and override tags are dropped.
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "srt" => Ok(SubtitleFormat::Srt),
+            "vtt" => Ok(SubtitleFormat::WebVtt),
+            "ass" | "ssa" => Ok(SubtitleFormat::Ass),
+            _ => Err(anyhow!(
+                "Unsupported subtitle file '{}': use .srt, .vtt, .ass or .ssa",
+                path.display()
+            )),
+        }
+    }
+}
+
+pub fn read(path: &Path, format: SubtitleFormat) -> Result<Vec<Cue>> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&String::from_utf8_lossy(&bytes), format)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn save(path: &Path, cues: &[Cue]) -> Result<()> {
+    let format = SubtitleFormat::from_path(path)?;
+    std::fs::write(path, write(cues, format))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn parse(text: &str, format: SubtitleFormat) -> Result<Vec<Cue>> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = match format {
+        SubtitleFormat::Srt | SubtitleFormat::WebVtt => parse_blocks(&text, format)?,
+        SubtitleFormat::Ass => parse_ass(&text)?,
+    };
+    cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(cues)
+}
+
+/// SRT and WebVTT share a layout: blank-line separated blocks with an
+/// optional identifier, a `start --> end` line and the text.
+fn parse_blocks(text: &str, format: SubtitleFormat) -> Result<Vec<Cue>> {
+    let mut blocks = text.split("\n\n").map(str::trim).filter(|b| !b.is_empty());
+    if format == SubtitleFormat::WebVtt {
+        let header = blocks.next().unwrap_or_default();
+        if !header.starts_with("WEBVTT") {
+            return Err(anyhow!("Missing WEBVTT header"));
+        }
+    }
+
+    let mut cues = Vec::new();
+    for block in blocks {
+        if format == SubtitleFormat::WebVtt
+            && ["NOTE", "STYLE", "REGION"]
+                .iter()
+                .any(|kw| block.starts_with(kw))
+        {
+            continue;
+        }
+        let mut lines = block.lines();
+        let mut timing = lines.next().unwrap_or_default();
+        if !timing.contains("-->") {
+            timing = lines.next().unwrap_or_default();
+        }
+        let (start, end) = timing
+            .split_once("-->")
+            .ok_or_else(|| anyhow!("Missing timing line in block '{}'", block))?;
+        // WebVTT cue settings (position, align, ...) follow the end time.
+        let end = end.split_whitespace().next().unwrap_or_default();
+        cues.push(Cue {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+            text: lines.collect::<Vec<_>>().join("\n"),
+        });
+    }
+    Ok(cues)
+}
+
+fn parse_ass(text: &str) -> Result<Vec<Cue>> {
+    let mut in_events = false;
+    let mut fields: Vec<String> = Vec::new();
+    let mut cues = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+        if let Some(format) = line.strip_prefix("Format:") {
+            fields = format
+                .split(',')
+                .map(|f| f.trim().to_ascii_lowercase())
+                .collect();
+        } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+            if fields.is_empty() {
+                return Err(anyhow!("Dialogue line before the [Events] Format line"));
+            }
+            // Text is the last field and may itself contain commas.
+            let values: Vec<&str> = dialogue.splitn(fields.len(), ',').collect();
+            let field = |name: &str| {
+                fields
+                    .iter()
+                    .position(|f| f == name)
+                    .and_then(|i| values.get(i))
+                    .map(|v| v.trim())
+                    .ok_or_else(|| anyhow!("Dialogue line without a {} field", name))
+            };
+            cues.push(Cue {
+                start: parse_time(field("start")?)?,
+                end: parse_time(field("end")?)?,
+                text: ass_to_plain(field("text")?),
+            });
+        }
+    }
+    Ok(cues)
+}
+
+/// Drops `{...}` override blocks and turns ASS line breaks into newlines.
+fn ass_to_plain(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_override = false;
+    for c in text.chars() {
+        match c {
+            '{' => in_override = true,
+            '}' if in_override => in_override = false,
+            _ if !in_override => plain.push(c),
+            _ => {}
+        }
+    }
+    plain
+        .replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ")
+}
+
+/// Parses `HH:MM:SS,mmm` (SRT), `[HH:]MM:SS.mmm` (WebVTT) or `H:MM:SS.cc`
+/// (ASS) into seconds.
+fn parse_time(value: &str) -> Result<f64> {
+    let value = value.trim();
+    let err = || anyhow!("Invalid subtitle timestamp '{}'", value);
+    let parts: Vec<&str> = value.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m, s] => (h.parse::<f64>().map_err(|_| err())?, *m, *s),
+        [m, s] => (0.0, *m, *s),
+        _ => return Err(err()),
+    };
+    let minutes: f64 = minutes.parse().map_err(|_| err())?;
+    let seconds: f64 = seconds.replace(',', ".").parse().map_err(|_| err())?;
+    Ok(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Formats seconds as `HH:MM:SS` plus milliseconds after `separator`.
+fn format_time(secs: f64, separator: char) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// ASS uses a single-digit hour and centiseconds.
+fn format_ass_time(secs: f64) -> String {
+    let centis = (secs.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+const ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1920
+PlayResY: 1080
+WrapStyle: 0
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,64,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,3,0,2,60,60,50,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+pub fn write(cues: &[Cue], format: SubtitleFormat) -> String {
+    let mut out = String::new();
+    match format {
+        SubtitleFormat::Srt => {
+            for (i, cue) in cues.iter().enumerate() {
+                writeln!(
+                    out,
+                    "{}\n{} --> {}\n{}\n",
+                    i + 1,
+                    format_time(cue.start, ','),
+                    format_time(cue.end, ','),
+                    cue.text
+                )
+                .unwrap();
+            }
+        }
+        SubtitleFormat::WebVtt => {
+            out.push_str("WEBVTT\n\n");
+            for cue in cues {
+                writeln!(
+                    out,
+                    "{} --> {}\n{}\n",
+                    format_time(cue.start, '.'),
+                    format_time(cue.end, '.'),
+                    cue.text
+                )
+                .unwrap();
+            }
+        }
+        SubtitleFormat::Ass => {
+            out.push_str(ASS_HEADER);
+            for cue in cues {
+                writeln!(
+                    out,
+                    "Dialogue: 0,{},{},Default,,0,0,0,,{}",
+                    format_ass_time(cue.start),
+                    format_ass_time(cue.end),
+                    cue.text.replace('\n', "\\N")
+                )
+                .unwrap();
+            }
+        }
+    }
+    out
+}
+
+/// Timing changes applied by `retime`, in this order: keep only the
+/// `trim_start..trim_end` range of the source and move it to zero, divide
+/// by `speed` (a timelapse factor), multiply by `stretch`, then add `shift`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retime {
+    pub trim_start: f64,
+    pub trim_end: Option<f64>,
+    pub speed: f64,
+    pub stretch: f64,
+    pub shift: f64,
+}
+
+impl Default for Retime {
+    fn default() -> Self {
+        Self {
+            trim_start: 0.0,
+            trim_end: None,
+            speed: 1.0,
+            stretch: 1.0,
+            shift: 0.0,
+        }
+    }
+}
+
+impl Retime {
+    fn map(&self, t: f64) -> f64 {
+        (t - self.trim_start) / self.speed * self.stretch + self.shift
+    }
+}
+
+/// Applies `retime` to every cue. Cues that end up entirely outside the
+/// trimmed range or before zero are dropped; ones that straddle an edge are
+/// clipped to it.
+pub fn retime(cues: &[Cue], retime: &Retime) -> Result<Vec<Cue>> {
+    if retime.speed <= 0.0 || retime.stretch <= 0.0 {
+        return Err(anyhow!(
+            "Speed and stretch factors must be greater than zero"
+        ));
+    }
+    let trim_end = retime.trim_end.unwrap_or(f64::INFINITY);
+    if trim_end <= retime.trim_start {
+        return Err(anyhow!("Trim end must be after the trim start"));
+    }
+
+    Ok(cues
+        .iter()
+        .filter(|cue| cue.end > retime.trim_start && cue.start < trim_end)
+        .map(|cue| Cue {
+            start: retime.map(cue.start.max(retime.trim_start)).max(0.0),
+            end: retime.map(cue.end.min(trim_end)),
+            text: cue.text.clone(),
+        })
+        .filter(|cue| cue.end > 0.0 && cue.end > cue.start)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_srt_with_ids_crlf_and_bom() {
+        let text = "\u{feff}1\r\n00:00:05,000 --> 00:00:08,500\r\nHello, world\r\nline two\r\n\r\n\
+                    2\r\n00:00:01,250 --> 00:00:02,000\r\nEarly\r\n";
+        let cues = parse(text, SubtitleFormat::Srt).unwrap();
+        assert_eq!(
+            cues,
+            vec![
+                cue(1.25, 2.0, "Early"),
+                cue(5.0, 8.5, "Hello, world\nline two"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_vtt_skipping_notes_and_cue_settings() {
+        let text = "WEBVTT - talk\n\n\
+                    NOTE this is a comment\n\n\
+                    intro\n00:01.000 --> 00:02.500 align:start position:10%\nHi\n\n\
+                    01:00:00.000 --> 01:00:01.000\n<i>Bye</i>\n";
+        let cues = parse(text, SubtitleFormat::WebVtt).unwrap();
+        assert_eq!(
+            cues,
+            vec![cue(1.0, 2.5, "Hi"), cue(3600.0, 3601.0, "<i>Bye</i>")]
+        );
+    }
+
+    #[test]
+    fn rejects_vtt_without_header() {
+        assert!(parse("00:01.000 --> 00:02.000\nHi\n", SubtitleFormat::WebVtt).is_err());
+    }
+
+    #[test]
+    fn parses_ass_dialogue_with_commas_and_overrides() {
+        let text = "[Script Info]\nTitle: test\n\n[Events]\n\
+                    Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                    Dialogue: 0,0:00:01.50,0:00:03.00,Default,,0,0,0,,{\\an8}Top, then\\Nmore, text\n\
+                    Comment: 0,0:00:04.00,0:00:05.00,Default,,0,0,0,,ignored\n";
+        let cues = parse(text, SubtitleFormat::Ass).unwrap();
+        assert_eq!(cues, vec![cue(1.5, 3.0, "Top, then\nmore, text")]);
+    }
+
+    #[test]
+    fn time_round_trips() {
+        let t = parse_time("01:02:03,456").unwrap();
+        assert!((t - 3723.456).abs() < 1e-9);
+        assert_eq!(format_time(t, ','), "01:02:03,456");
+        assert_eq!(format_time(t, '.'), "01:02:03.456");
+        assert_eq!(parse_time(&format_time(t, '.')).unwrap(), t);
+
+        let a = parse_time("1:02:03.45").unwrap();
+        assert_eq!(format_ass_time(a), "1:02:03.45");
+        assert_eq!(parse_time("02:03.450").unwrap(), 123.45);
+        assert!(parse_time("soon").is_err());
+    }
+
+    #[test]
+    fn write_then_parse_round_trips() {
+        let cues = vec![cue(0.5, 1.5, "One"), cue(2.0, 3.25, "Two\nlines")];
+        for format in [
+            SubtitleFormat::Srt,
+            SubtitleFormat::WebVtt,
+            SubtitleFormat::Ass,
+        ] {
+            assert_eq!(parse(&write(&cues, format), format).unwrap(), cues);
+        }
+    }
+
+    #[test]
+    fn retime_clips_at_trim_edges() {
+        let cues = vec![
+            cue(1.0, 3.0, "before"),
+            cue(9.0, 11.0, "straddles start"),
+            cue(15.0, 25.0, "straddles end"),
+            cue(30.0, 31.0, "after"),
+        ];
+        let timing = Retime {
+            trim_start: 10.0,
+            trim_end: Some(20.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            retime(&cues, &timing).unwrap(),
+            vec![
+                cue(0.0, 1.0, "straddles start"),
+                cue(5.0, 10.0, "straddles end")
+            ]
+        );
+    }
+
+    #[test]
+    fn retime_applies_speed_and_stretch() {
+        let cues = vec![cue(10.0, 20.0, "a")];
+        let timing = Retime {
+            speed: 4.0,
+            ..Default::default()
+        };
+        assert_eq!(retime(&cues, &timing).unwrap(), vec![cue(2.5, 5.0, "a")]);
+        let timing = Retime {
+            stretch: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(retime(&cues, &timing).unwrap(), vec![cue(15.0, 30.0, "a")]);
+    }
+
+    #[test]
+    fn retime_negative_shift_drops_and_clamps() {
+        let cues = vec![
+            cue(0.5, 1.0, "gone"),
+            cue(1.5, 3.0, "clamped"),
+            cue(5.0, 6.0, "moved"),
+        ];
+        let timing = Retime {
+            shift: -2.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            retime(&cues, &timing).unwrap(),
+            vec![cue(0.0, 1.0, "clamped"), cue(3.0, 4.0, "moved")]
+        );
+    }
+
+    #[test]
+    fn retime_rejects_bad_factors() {
+        let cues = vec![cue(0.0, 1.0, "a")];
+        let zero_speed = Retime {
+            speed: 0.0,
+            ..Default::default()
+        };
+        assert!(retime(&cues, &zero_speed).is_err());
+        let inverted = Retime {
+            trim_start: 5.0,
+            trim_end: Some(2.0),
+            ..Default::default()
+        };
+        assert!(retime(&cues, &inverted).is_err());
+    }
+}
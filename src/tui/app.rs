@@ -1,20 +1,22 @@
+use crate::commands;
+use crate::probe::{self, MediaInfo};
 use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use super::AppEvent;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
 pub enum ActiveTab {
+    #[default]
     Combine,
     Compress,
     AddMusic,
     Timelapse,
+    Trim,
     Info,
 }
 
-impl Default for ActiveTab {
-    fn default() -> Self {
-        ActiveTab::Combine
-    }
-}
-
 #[derive(Debug, Default, Clone)]
 pub struct InputField {
     pub value: String,
@@ -28,19 +30,49 @@ pub struct App {
 
     pub combine_inputs: InputField,
     pub combine_output: InputField,
+    pub combine_target: InputField,
+    pub combine_mode: InputField,
+    pub combine_transition: InputField,
+    pub combine_transition_duration: InputField,
 
     pub compress_input: InputField,
     pub compress_output: InputField,
     pub compress_crf: InputField,
+    pub compress_target_size: InputField,
+    pub compress_codec: InputField,
+    pub compress_preset: InputField,
+    pub compress_max_size: InputField,
+    pub compress_max_fps: InputField,
+    /// Last probed compress input, so the dimension preview doesn't re-run
+    /// ffprobe on every frame. The info is `None` until the probe finishes.
+    compress_probe: Option<(String, Option<MediaInfo>)>,
 
     pub music_video: InputField,
     pub music_audio: InputField,
     pub music_output: InputField,
     pub music_reduce: InputField,
+    pub music_loop: InputField,
+    pub music_start: InputField,
+    pub music_delay: InputField,
+    pub music_fade_in: InputField,
+    pub music_fade_out: InputField,
+    pub music_duck: InputField,
 
     pub time_input: InputField,
     pub time_output: InputField,
     pub time_speed: InputField,
+    pub time_method: InputField,
+    pub time_audio: InputField,
+    pub time_interpolate: InputField,
+    pub time_fps: InputField,
+
+    pub trim_input: InputField,
+    pub trim_output: InputField,
+    pub trim_start: InputField,
+    pub trim_end: InputField,
+    pub trim_duration: InputField,
+    pub trim_mode: InputField,
+
     pub info_input: InputField,
 
     pub message: String,
@@ -66,6 +98,22 @@ impl App {
                 label: "Output Path".into(),
                 ..Default::default()
             },
+            combine_target: InputField {
+                label: "Target Canvas (first/largest/WxH@fps)".into(),
+                value: "first".into(),
+            },
+            combine_mode: InputField {
+                label: "Mode (auto/copy/reencode)".into(),
+                value: "auto".into(),
+            },
+            combine_transition: InputField {
+                label: "Transitions (none, fade, wipeleft... or one per join)".into(),
+                value: "none".into(),
+            },
+            combine_transition_duration: InputField {
+                label: "Transition Duration (seconds)".into(),
+                value: "1.0".into(),
+            },
 
             compress_input: InputField {
                 label: "Input Video".into(),
@@ -79,6 +127,27 @@ impl App {
                 label: "CRF (0-51, Default: 23)".into(),
                 value: "23".into(),
             },
+            compress_target_size: InputField {
+                label: "Target Size (e.g. 25MB, empty to use CRF)".into(),
+                ..Default::default()
+            },
+            compress_codec: InputField {
+                label: "Codec (h264/h265/vp9/av1)".into(),
+                value: "h264".into(),
+            },
+            compress_preset: InputField {
+                label: "Preset (e.g. medium, slow; empty for default)".into(),
+                ..Default::default()
+            },
+            compress_max_size: InputField {
+                label: "Max Size (e.g. 1920x1080 or x720, empty to keep)".into(),
+                ..Default::default()
+            },
+            compress_max_fps: InputField {
+                label: "Max FPS (empty to keep)".into(),
+                ..Default::default()
+            },
+            compress_probe: None,
 
             music_video: InputField {
                 label: "Video Path".into(),
@@ -96,9 +165,33 @@ impl App {
                 label: "Original Volume (0.0-1.0)".into(),
                 value: "1.0".into(),
             },
+            music_loop: InputField {
+                label: "Loop Music (yes/no)".into(),
+                value: "no".into(),
+            },
+            music_start: InputField {
+                label: "Start Into Track (HH:MM:SS or seconds)".into(),
+                value: "0".into(),
+            },
+            music_delay: InputField {
+                label: "Delay In Video (HH:MM:SS or seconds)".into(),
+                value: "0".into(),
+            },
+            music_fade_in: InputField {
+                label: "Fade In (seconds)".into(),
+                value: "0".into(),
+            },
+            music_fade_out: InputField {
+                label: "Fade Out (seconds)".into(),
+                value: "0".into(),
+            },
+            music_duck: InputField {
+                label: "Ducking (off/voiceover/vlog)".into(),
+                value: "off".into(),
+            },
 
             time_input: InputField {
-                label: "Input Video".into(),
+                label: "Input Video or Photo Folder".into(),
                 ..Default::default()
             },
             time_output: InputField {
@@ -109,6 +202,47 @@ impl App {
                 label: "Speed Factor".into(),
                 value: "10.0".into(),
             },
+            time_method: InputField {
+                label: "Method (setpts/sample/blend)".into(),
+                value: "setpts".into(),
+            },
+            time_audio: InputField {
+                label: "Audio (drop/preserve-pitch/shift-pitch)".into(),
+                value: "drop".into(),
+            },
+            time_interpolate: InputField {
+                label: "Interpolate Slow Motion (yes/no)".into(),
+                value: "no".into(),
+            },
+            time_fps: InputField {
+                label: "Output FPS (empty to keep)".into(),
+                ..Default::default()
+            },
+
+            trim_input: InputField {
+                label: "Input Video".into(),
+                ..Default::default()
+            },
+            trim_output: InputField {
+                label: "Output Path".into(),
+                ..Default::default()
+            },
+            trim_start: InputField {
+                label: "Start (HH:MM:SS or seconds)".into(),
+                value: "0".into(),
+            },
+            trim_end: InputField {
+                label: "End (leave empty to use Duration)".into(),
+                ..Default::default()
+            },
+            trim_duration: InputField {
+                label: "Duration (leave empty to use End)".into(),
+                ..Default::default()
+            },
+            trim_mode: InputField {
+                label: "Mode (copy/accurate)".into(),
+                value: "copy".into(),
+            },
 
             info_input: InputField {
                 label: "Video Path".into(),
@@ -129,23 +263,58 @@ impl App {
             ActiveTab::Combine => ActiveTab::Compress,
             ActiveTab::Compress => ActiveTab::AddMusic,
             ActiveTab::AddMusic => ActiveTab::Timelapse,
-            ActiveTab::Timelapse => ActiveTab::Info,
+            ActiveTab::Timelapse => ActiveTab::Trim,
+            ActiveTab::Trim => ActiveTab::Info,
             ActiveTab::Info => ActiveTab::Combine,
         };
         self.selected_field = 0;
         self.message.clear();
     }
 
-    pub fn prev_tab(&mut self) {
-        self.active_tab = match self.active_tab {
-            ActiveTab::Combine => ActiveTab::Info,
-            ActiveTab::Compress => ActiveTab::Combine,
-            ActiveTab::AddMusic => ActiveTab::Compress,
-            ActiveTab::Timelapse => ActiveTab::AddMusic,
-            ActiveTab::Info => ActiveTab::Timelapse,
-        };
-        self.selected_field = 0;
-        self.message.clear();
+    /// Probes the compress input on a worker thread when its path changes to
+    /// a readable file; the result comes back as `AppEvent::Probed`.
+    pub fn refresh_compress_probe(&mut self, tx: &mpsc::Sender<AppEvent>) {
+        let path = self.compress_input.value.trim();
+        if self.compress_probe.as_ref().map(|(p, _)| p.as_str()) == Some(path) {
+            return;
+        }
+        self.compress_probe = Some((path.to_string(), None));
+        if Path::new(path).is_file() {
+            let path = path.to_string();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let info = probe::probe(Path::new(&path)).ok();
+                let _ = tx.send(AppEvent::Probed(path, info));
+            });
+        }
+    }
+
+    /// Stores a finished probe, unless the input has changed since it started.
+    pub fn set_compress_probe(&mut self, path: String, info: Option<MediaInfo>) {
+        if let Some((current, slot)) = &mut self.compress_probe {
+            if *current == path {
+                *slot = info;
+            }
+        }
+    }
+
+    /// "Source -> target" dimensions for the Compress tab, if the input has
+    /// been probed.
+    pub fn compress_preview(&self) -> Option<String> {
+        let info = self.compress_probe.as_ref()?.1.as_ref()?;
+        let video = info.video()?;
+        let (w, h) = video.display_size()?;
+
+        let (max_w, max_h) = commands::parse_max_size(&self.compress_max_size.value).ok()?;
+        let max_fps = commands::parse_max_fps(&self.compress_max_fps.value).ok()?;
+        let (tw, th) = commands::capped_dimensions(w, h, max_w, max_h);
+        let fps = video.fps.unwrap_or(0.0);
+        let target_fps = commands::capped_fps(video.fps, max_fps).unwrap_or(fps);
+
+        Some(format!(
+            "Source: {}x{} @ {:.2} fps  ->  Target: {}x{} @ {:.2} fps",
+            w, h, fps, tw, th, target_fps
+        ))
     }
 
     pub fn next_field(&mut self) {
@@ -168,10 +337,11 @@ impl App {
 
     fn get_field_count(&self) -> usize {
         match self.active_tab {
-            ActiveTab::Combine => 2,
-            ActiveTab::Compress => 3,
-            ActiveTab::AddMusic => 4,
-            ActiveTab::Timelapse => 3,
+            ActiveTab::Combine => 6,
+            ActiveTab::Compress => 8,
+            ActiveTab::AddMusic => 10,
+            ActiveTab::Timelapse => 7,
+            ActiveTab::Trim => 6,
             ActiveTab::Info => 1,
         }
     }
@@ -236,12 +406,21 @@ impl App {
             ActiveTab::Combine => match self.selected_field {
                 0 => &mut self.combine_inputs,
                 1 => &mut self.combine_output,
+                2 => &mut self.combine_target,
+                3 => &mut self.combine_mode,
+                4 => &mut self.combine_transition,
+                5 => &mut self.combine_transition_duration,
                 _ => &mut self.combine_inputs,
             },
             ActiveTab::Compress => match self.selected_field {
                 0 => &mut self.compress_input,
                 1 => &mut self.compress_output,
                 2 => &mut self.compress_crf,
+                3 => &mut self.compress_target_size,
+                4 => &mut self.compress_codec,
+                5 => &mut self.compress_preset,
+                6 => &mut self.compress_max_size,
+                7 => &mut self.compress_max_fps,
                 _ => &mut self.compress_input,
             },
             ActiveTab::AddMusic => match self.selected_field {
@@ -249,14 +428,33 @@ impl App {
                 1 => &mut self.music_audio,
                 2 => &mut self.music_output,
                 3 => &mut self.music_reduce,
+                4 => &mut self.music_loop,
+                5 => &mut self.music_start,
+                6 => &mut self.music_delay,
+                7 => &mut self.music_fade_in,
+                8 => &mut self.music_fade_out,
+                9 => &mut self.music_duck,
                 _ => &mut self.music_video,
             },
             ActiveTab::Timelapse => match self.selected_field {
                 0 => &mut self.time_input,
                 1 => &mut self.time_output,
                 2 => &mut self.time_speed,
+                3 => &mut self.time_method,
+                4 => &mut self.time_audio,
+                5 => &mut self.time_interpolate,
+                6 => &mut self.time_fps,
                 _ => &mut self.time_input,
             },
+            ActiveTab::Trim => match self.selected_field {
+                0 => &mut self.trim_input,
+                1 => &mut self.trim_output,
+                2 => &mut self.trim_start,
+                3 => &mut self.trim_end,
+                4 => &mut self.trim_duration,
+                5 => &mut self.trim_mode,
+                _ => &mut self.trim_input,
+            },
             ActiveTab::Info => &mut self.info_input,
         }
     }
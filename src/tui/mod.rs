@@ -2,7 +2,7 @@ pub mod app;
 pub mod events;
 pub mod ui;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
@@ -15,12 +15,14 @@ use std::sync::mpsc;
 use std::thread;
 
 use crate::commands::{self, ProgressInfo};
+use crate::probe::MediaInfo;
 use app::{ActiveTab, App};
 use events::handle_events;
 use ui::render;
 
 pub enum AppEvent {
     Progress(ProgressInfo),
+    Probed(String, Option<MediaInfo>),
     Done,
     Error(String),
 }
@@ -36,6 +38,9 @@ pub fn run() -> Result<()> {
     let (tx, rx) = mpsc::channel();
 
     loop {
+        if app.active_tab == ActiveTab::Compress {
+            app.refresh_compress_probe(&tx);
+        }
         terminal.draw(|f| render(f, &app))?;
 
         while let Ok(event) = rx.try_recv() {
@@ -44,6 +49,7 @@ pub fn run() -> Result<()> {
                     ProgressInfo::Log(log) => app.logs.push(log),
                     ProgressInfo::Percentage(p) => app.progress = p,
                 },
+                AppEvent::Probed(path, info) => app.set_compress_probe(path, info),
                 AppEvent::Done => {
                     app.is_processing = true;
                     app.is_complete = true;
@@ -95,19 +101,45 @@ fn execute_command(app: &App, tx: mpsc::Sender<AppEvent>) {
     let active_tab = app.active_tab;
     let combine_inputs = app.combine_inputs.value.clone();
     let combine_output = app.combine_output.value.clone();
+    let combine_target = app.combine_target.value.clone();
+    let combine_mode = app.combine_mode.value.clone();
+    let combine_transition = app.combine_transition.value.clone();
+    let combine_transition_duration = app.combine_transition_duration.value.clone();
 
     let compress_input = app.compress_input.value.clone();
     let compress_output = app.compress_output.value.clone();
     let compress_crf = app.compress_crf.value.clone();
+    let compress_target_size = app.compress_target_size.value.clone();
+    let compress_codec = app.compress_codec.value.clone();
+    let compress_preset = app.compress_preset.value.clone();
+    let compress_max_size = app.compress_max_size.value.clone();
+    let compress_max_fps = app.compress_max_fps.value.clone();
 
     let music_video = app.music_video.value.clone();
     let music_audio = app.music_audio.value.clone();
     let music_output = app.music_output.value.clone();
     let music_reduce = app.music_reduce.value.clone();
+    let music_loop = app.music_loop.value.clone();
+    let music_start = app.music_start.value.clone();
+    let music_delay = app.music_delay.value.clone();
+    let music_fade_in = app.music_fade_in.value.clone();
+    let music_fade_out = app.music_fade_out.value.clone();
+    let music_duck = app.music_duck.value.clone();
 
     let time_input = app.time_input.value.clone();
     let time_output = app.time_output.value.clone();
     let time_speed = app.time_speed.value.clone();
+    let time_method = app.time_method.value.clone();
+    let time_audio = app.time_audio.value.clone();
+    let time_interpolate = app.time_interpolate.value.clone();
+    let time_fps = app.time_fps.value.clone();
+
+    let trim_input = app.trim_input.value.clone();
+    let trim_output = app.trim_output.value.clone();
+    let trim_start = app.trim_start.value.clone();
+    let trim_end = app.trim_end.value.clone();
+    let trim_duration = app.trim_duration.value.clone();
+    let trim_mode = app.trim_mode.value.clone();
 
     let info_input = app.info_input.value.clone();
 
@@ -119,38 +151,163 @@ fn execute_command(app: &App, tx: mpsc::Sender<AppEvent>) {
                     .map(PathBuf::from)
                     .collect();
                 let output = Path::new(&combine_output);
-                commands::combine_videos(&inputs, output, |info| {
-                    let _ = tx.send(AppEvent::Progress(info));
-                })
+                combine_target
+                    .parse()
+                    .and_then(|target| {
+                        let duration = match combine_transition_duration.trim() {
+                            "" => 1.0,
+                            v => v
+                                .parse()
+                                .map_err(|_| anyhow!("Invalid transition duration: '{}'", v))?,
+                        };
+                        Ok(commands::CombineOptions {
+                            target,
+                            mode: combine_mode.parse()?,
+                            transitions: commands::parse_transitions(
+                                &combine_transition,
+                                duration,
+                            )?,
+                        })
+                    })
+                    .and_then(|options| {
+                        commands::combine_videos(&inputs, output, &options, |info| {
+                            let _ = tx.send(AppEvent::Progress(info));
+                        })
+                    })
             }
             ActiveTab::Compress => {
                 let input = Path::new(&compress_input);
                 let output = Path::new(&compress_output);
                 let crf: u8 = compress_crf.parse().unwrap_or(23);
-                commands::compress_video(input, output, crf, |info| {
-                    let _ = tx.send(AppEvent::Progress(info));
-                })
+                let target_size = compress_target_size.trim();
+                (!target_size.is_empty())
+                    .then(|| commands::parse_size(target_size))
+                    .transpose()
+                    .and_then(|target_size| {
+                        let preset = compress_preset.trim();
+                        let (max_width, max_height) = commands::parse_max_size(&compress_max_size)?;
+                        let options = commands::CompressOptions {
+                            crf,
+                            target_size,
+                            codec: compress_codec.parse()?,
+                            preset: (!preset.is_empty()).then(|| preset.to_string()),
+                            max_width,
+                            max_height,
+                            max_fps: commands::parse_max_fps(&compress_max_fps)?,
+                            ..Default::default()
+                        };
+                        commands::compress_video(input, output, &options, |info| {
+                            let _ = tx.send(AppEvent::Progress(info));
+                        })
+                    })
             }
             ActiveTab::AddMusic => {
                 let video = Path::new(&music_video);
                 let audio = Path::new(&music_audio);
                 let output = Path::new(&music_output);
-                let reduce = &music_reduce;
-                commands::add_music(video, audio, output, reduce, |info| {
-                    let _ = tx.send(AppEvent::Progress(info));
+                let timestamp = |value: &str| match value.trim() {
+                    "" => Ok(0.0),
+                    v => commands::parse_timestamp(v),
+                };
+                let volume = match music_reduce.trim() {
+                    "" => Ok("1.0".to_string()),
+                    v => commands::parse_volume(v),
+                };
+                volume.and_then(|reduce_original| {
+                    let duck = match music_duck.trim() {
+                        "" | "off" => None,
+                        preset => Some(preset.parse()?),
+                    };
+                    let options = commands::MusicOptions {
+                        reduce_original,
+                        loop_music: matches!(
+                            music_loop.trim().to_ascii_lowercase().as_str(),
+                            "yes" | "y" | "true"
+                        ),
+                        music_start: timestamp(&music_start)?,
+                        delay: timestamp(&music_delay)?,
+                        fade_in: timestamp(&music_fade_in)?,
+                        fade_out: timestamp(&music_fade_out)?,
+                        duck,
+                    };
+                    commands::add_music(video, audio, output, &options, |info| {
+                        let _ = tx.send(AppEvent::Progress(info));
+                    })
                 })
             }
             ActiveTab::Timelapse => {
                 let input = Path::new(&time_input);
                 let output = Path::new(&time_output);
-                let speed: f64 = time_speed.parse().unwrap_or(10.0);
-                commands::timelapse(input, output, speed, |info| {
-                    let _ = tx.send(AppEvent::Progress(info));
-                })
+                let speed = match time_speed.trim() {
+                    "" => Ok(10.0),
+                    v => v.parse().map_err(|_| anyhow!("Invalid speed: '{}'", v)),
+                };
+                let fps = match time_fps.trim() {
+                    "" => Ok(None),
+                    v => v
+                        .parse::<f64>()
+                        .map(Some)
+                        .map_err(|_| anyhow!("Invalid fps: '{}'", v)),
+                };
+                if input.is_dir() {
+                    fps.and_then(|fps| {
+                        let defaults = commands::ImageSequenceOptions::default();
+                        let options = commands::ImageSequenceOptions {
+                            fps: fps.unwrap_or(defaults.fps),
+                            ..defaults
+                        };
+                        commands::timelapse_from_images(input, output, &options, |info| {
+                            let _ = tx.send(AppEvent::Progress(info));
+                        })
+                    })
+                } else {
+                    time_method
+                        .parse()
+                        .and_then(|method| Ok((method, time_audio.parse()?)))
+                        .and_then(|(method, audio)| {
+                            let options = commands::TimelapseOptions {
+                                speed: speed?,
+                                method,
+                                audio,
+                                interpolate: matches!(
+                                    time_interpolate.trim().to_ascii_lowercase().as_str(),
+                                    "yes" | "y" | "true"
+                                ),
+                                fps: fps?,
+                                ..Default::default()
+                            };
+                            commands::timelapse(input, output, &options, |info| {
+                                let _ = tx.send(AppEvent::Progress(info));
+                            })
+                        })
+                }
+            }
+            ActiveTab::Trim => {
+                let input = Path::new(&trim_input);
+                let output = Path::new(&trim_output);
+                let non_empty = |s: &str| {
+                    let s = s.trim();
+                    (!s.is_empty()).then(|| s.to_string())
+                };
+                let start = non_empty(&trim_start);
+                let end = non_empty(&trim_end);
+                let duration = non_empty(&trim_duration);
+                let accurate = trim_mode.trim().eq_ignore_ascii_case("accurate");
+                commands::trim_video(
+                    input,
+                    output,
+                    start.as_deref(),
+                    end.as_deref(),
+                    duration.as_deref(),
+                    accurate,
+                    |info| {
+                        let _ = tx.send(AppEvent::Progress(info));
+                    },
+                )
             }
             ActiveTab::Info => {
                 let input = Path::new(&info_input);
-                commands::get_info(input, |info| {
+                commands::get_info(input, false, |info| {
                     let _ = tx.send(AppEvent::Progress(info));
                 })
             }
@@ -1,4 +1,4 @@
-use crate::tui::app::{ActiveTab, App};
+use crate::tui::app::{ActiveTab, App, InputField};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -55,7 +55,14 @@ fn render_message(frame: &mut Frame, app: &App, area: Rect) {
 }
 
 fn render_tabs(frame: &mut Frame, app: &App, area: Rect) {
-    let titles_list = vec!["Combine", "Compress", "Add Music", "Fast Forward", "Info"];
+    let titles_list = [
+        "Combine",
+        "Compress",
+        "Add Music",
+        "Fast Forward",
+        "Trim",
+        "Info",
+    ];
     let inner_width = area.width.saturating_sub(2) as usize;
     let tab_width = inner_width / titles_list.len();
 
@@ -96,6 +103,7 @@ fn render_content(frame: &mut Frame, app: &App, area: Rect) {
         ActiveTab::Compress => render_compress(frame, app, area),
         ActiveTab::AddMusic => render_add_music(frame, app, area),
         ActiveTab::Timelapse => render_timelapse(frame, app, area),
+        ActiveTab::Trim => render_trim(frame, app, area),
         ActiveTab::Info => render_info(frame, app, area),
     }
 }
@@ -122,133 +130,104 @@ fn render_input(frame: &mut Frame, label: &str, value: &str, is_selected: bool,
     frame.render_widget(paragraph, area);
 }
 
-fn render_combine(frame: &mut Frame, app: &App, area: Rect) {
+/// Renders a column of input fields, scrolling so the selected one stays
+/// visible when the area is too short to show them all.
+fn render_form(frame: &mut Frame, app: &App, fields: &[&InputField], area: Rect) {
+    let visible = (area.height / 3).max(1) as usize;
+    let first = (app.selected_field + 1).saturating_sub(visible);
+    let shown = fields.iter().enumerate().skip(first).take(visible);
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Length(3)])
+        .constraints(shown.clone().map(|_| Constraint::Length(3)))
         .split(area);
 
-    render_input(
-        frame,
-        &app.combine_inputs.label,
-        &app.combine_inputs.value,
-        app.selected_field == 0,
-        chunks[0],
-    );
-    render_input(
-        frame,
-        &app.combine_output.label,
-        &app.combine_output.value,
-        app.selected_field == 1,
-        chunks[1],
-    );
+    for ((i, field), chunk) in shown.zip(chunks.iter()) {
+        render_input(
+            frame,
+            &field.label,
+            &field.value,
+            app.selected_field == i,
+            *chunk,
+        );
+    }
+}
+
+fn render_combine(frame: &mut Frame, app: &App, area: Rect) {
+    let fields = [
+        &app.combine_inputs,
+        &app.combine_output,
+        &app.combine_target,
+        &app.combine_mode,
+        &app.combine_transition,
+        &app.combine_transition_duration,
+    ];
+    render_form(frame, app, &fields, area);
 }
 
 fn render_compress(frame: &mut Frame, app: &App, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-        ])
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
         .split(area);
 
-    render_input(
-        frame,
-        &app.compress_input.label,
-        &app.compress_input.value,
-        app.selected_field == 0,
-        chunks[0],
-    );
-    render_input(
-        frame,
-        &app.compress_output.label,
-        &app.compress_output.value,
-        app.selected_field == 1,
-        chunks[1],
-    );
-    render_input(
-        frame,
-        &app.compress_crf.label,
-        &app.compress_crf.value,
-        app.selected_field == 2,
-        chunks[2],
-    );
+    let fields = [
+        &app.compress_input,
+        &app.compress_output,
+        &app.compress_crf,
+        &app.compress_target_size,
+        &app.compress_codec,
+        &app.compress_preset,
+        &app.compress_max_size,
+        &app.compress_max_fps,
+    ];
+    render_form(frame, app, &fields, chunks[0]);
+
+    if let Some(preview) = app.compress_preview() {
+        let paragraph = Paragraph::new(preview).style(Style::default().fg(Color::Cyan));
+        frame.render_widget(paragraph, chunks[1]);
+    }
 }
 
 fn render_add_music(frame: &mut Frame, app: &App, area: Rect) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-        ])
-        .split(area);
-
-    render_input(
-        frame,
-        &app.music_video.label,
-        &app.music_video.value,
-        app.selected_field == 0,
-        chunks[0],
-    );
-    render_input(
-        frame,
-        &app.music_audio.label,
-        &app.music_audio.value,
-        app.selected_field == 1,
-        chunks[1],
-    );
-    render_input(
-        frame,
-        &app.music_output.label,
-        &app.music_output.value,
-        app.selected_field == 2,
-        chunks[2],
-    );
-    render_input(
-        frame,
-        &app.music_reduce.label,
-        &app.music_reduce.value,
-        app.selected_field == 3,
-        chunks[3],
-    );
+    let fields = [
+        &app.music_video,
+        &app.music_audio,
+        &app.music_output,
+        &app.music_reduce,
+        &app.music_loop,
+        &app.music_start,
+        &app.music_delay,
+        &app.music_fade_in,
+        &app.music_fade_out,
+        &app.music_duck,
+    ];
+    render_form(frame, app, &fields, area);
 }
 
 fn render_timelapse(frame: &mut Frame, app: &App, area: Rect) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-        ])
-        .split(area);
+    let fields = [
+        &app.time_input,
+        &app.time_output,
+        &app.time_speed,
+        &app.time_method,
+        &app.time_audio,
+        &app.time_interpolate,
+        &app.time_fps,
+    ];
+    render_form(frame, app, &fields, area);
+}
 
-    render_input(
-        frame,
-        &app.time_input.label,
-        &app.time_input.value,
-        app.selected_field == 0,
-        chunks[0],
-    );
-    render_input(
-        frame,
-        &app.time_output.label,
-        &app.time_output.value,
-        app.selected_field == 1,
-        chunks[1],
-    );
-    render_input(
-        frame,
-        &app.time_speed.label,
-        &app.time_speed.value,
-        app.selected_field == 2,
-        chunks[2],
-    );
+fn render_trim(frame: &mut Frame, app: &App, area: Rect) {
+    let fields = [
+        &app.trim_input,
+        &app.trim_output,
+        &app.trim_start,
+        &app.trim_end,
+        &app.trim_duration,
+        &app.trim_mode,
+    ];
+    render_form(frame, app, &fields, area);
 }
 
 fn render_info(frame: &mut Frame, app: &App, area: Rect) {